@@ -1,7 +1,7 @@
-///
-/// This example shows a basic usecase of searching for a specific school
-/// and then retrieving a user's own timetable.
-///
+//!
+//! This example shows a basic usecase of searching for a specific school
+//! and then retrieving a user's own timetable.
+//!
 
 fn main() -> Result<(), untis::Error> {
     // Get the school by its id.
@@ -14,13 +14,11 @@ fn main() -> Result<(), untis::Error> {
     // Match the result to handle specific error cases.
     match result {
         Ok(v) => client = v,
-        Err(untis::Error::Rpc(err)) => {
-            if err.code == untis::jsonrpc::ErrorCode::InvalidCredentials.as_isize() {
-                println!("Invalid credentials");
-            }
-            return Err(untis::Error::Rpc(err));
+        Err(err) if err.rpc_code() == Some(untis::jsonrpc::ErrorCode::InvalidCredentials) => {
+            println!("Invalid credentials");
+            return Err(err);
         }
-        Err(err) => return Err(err)?,
+        Err(err) => return Err(err),
     };
 
     let date = chrono::Local::now().date_naive() + chrono::Duration::weeks(2);
@@ -0,0 +1,20 @@
+//!
+//! This example shows how to use the asynchronous client to retrieve
+//! a user's own timetable.
+//!
+
+#[tokio::main]
+async fn main() -> Result<(), untis::Error> {
+    // Search for the school without blocking the runtime.
+    let school = untis::schools::get_by_name_async("SchoolName").await?;
+
+    let mut client = school.client_login_async("username", "password").await?;
+
+    let timetable = client.own_timetable_current_week().await?;
+
+    for lesson in timetable {
+        println!("{:?}", lesson);
+    }
+
+    Ok(())
+}
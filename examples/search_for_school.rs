@@ -1,6 +1,6 @@
-///
-/// This example shows a basic usecase of searching for schools.
-///
+//!
+//! This example shows a basic usecase of searching for schools.
+//!
 
 fn main() -> Result<(), untis::Error> {
     let schools = untis::schools::search("query")?;
@@ -1,13 +1,19 @@
-///
-/// This example shows how you can access a list of your school's teachers and how
-/// you can view the timetables of other people/assets (i.e. rooms). Note that your
-/// school likely has restrictions in place for accessing other timetables.
-///
+//!
+//! This example shows how you can access a list of your school's teachers and how
+//! you can view the timetables of other people/assets (i.e. rooms). Note that your
+//! school likely has restrictions in place for accessing other timetables.
+//!
 
 fn main() -> Result<(), untis::Error> {
     // Log in by specifying the school's details and credentials manually.
-    let mut client =
-        untis::Client::login("server.webuntis.com", "SchoolName", "username", "password")?;
+    // The loop below sends one request per teacher, so limit the request rate to avoid getting blocked.
+    let mut client = untis::ClientBuilder::new()
+        .rate_limit(untis::RateLimit::new(5.0).burst(10))
+        .login(
+            "server.webuntis.com",
+            "SchoolName",
+            untis::Credentials::password("username", "password"),
+        )?;
 
     // Retrieve a list of teachers at the user's school.
     let teachers = client.teachers()?;
@@ -0,0 +1,1478 @@
+use chrono::TimeZone;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::{self, Debug, Formatter};
+
+use crate::{
+    batch::{Batch, BatchResults},
+    cache::{self, Cache},
+    datetime::Date,
+    error::{Error, ErrorKind},
+    jsonrpc, otp, params,
+    resources::*,
+    CachePolicy, ClientBuilder, Session, SessionToken, SetupLink,
+};
+
+/// Asynchronous client for accessing the Untis API. Can be constructed by [`AsyncClient::login()`](Self::login) or
+/// [`School::client_login_async()`](School::client_login_async).
+///
+#[cfg_attr(
+    feature = "blocking",
+    doc = "Offers the same methods as the blocking [`Client`](crate::Client), but has to be driven by a"
+)]
+#[cfg_attr(
+    not(feature = "blocking"),
+    doc = "Offers the same methods as the blocking `Client` of the `blocking` feature, but has to be driven by a"
+)]
+/// [tokio](https://tokio.rs) runtime.
+///
+/// # Example
+/// ```rust,no_run
+/// # async fn run() {
+/// let result = untis::AsyncClient::login("server.webuntis.com", "school", "username", "password").await;
+/// match result {
+///     Err(err) => println!("{}", err),
+///     Ok(client) => {
+///         let info = client.session();
+///     }
+/// }
+/// # }
+/// ```
+pub struct AsyncClient {
+    rpc_client: jsonrpc::Client,
+    endpoint: Endpoint,
+    session: Session,
+    credentials: Option<Credentials>,
+    pub(crate) logout_on_drop: bool,
+    lenient: bool,
+    skipped: Vec<SkippedEntry>,
+    cache: Option<Cache>,
+}
+
+/// Credentials that can be used to authenticate again once a session has expired.
+/// See [`AsyncClient::enable_reauthentication()`].
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub enum Credentials {
+    /// A user's name and password.
+    Password { username: String, password: String },
+
+    /// A user's name and the app secret that the WebUntis app uses to generate one-time passwords.
+    Secret { username: String, secret: String },
+
+    /// Anonymous guest access, see [`AsyncClient::login_anonymous()`].
+    Anonymous,
+}
+
+impl Credentials {
+    /// Shorthand for creating [`Credentials::Password`].
+    pub fn password(username: &str, password: &str) -> Self {
+        Self::Password {
+            username: username.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    /// Shorthand for creating [`Credentials::Secret`].
+    pub fn secret(username: &str, secret: &str) -> Self {
+        Self::Secret {
+            username: username.to_string(),
+            secret: secret.to_string(),
+        }
+    }
+}
+
+/// A list entry that was skipped in [lenient mode](AsyncClient::set_lenient) because it couldn't be parsed.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SkippedEntry {
+    /// The method whose result contained the entry.
+    pub method: String,
+
+    /// The entry as sent by Untis.
+    pub entry: serde_json::Value,
+
+    /// Why the entry couldn't be parsed.
+    pub error: String,
+}
+
+impl Debug for Credentials {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Password { username, .. } => formatter
+                .debug_struct("Password")
+                .field("username", username)
+                .field("password", &"<redacted>")
+                .finish(),
+            Self::Secret { username, .. } => formatter
+                .debug_struct("Secret")
+                .field("username", username)
+                .field("secret", &"<redacted>")
+                .finish(),
+            Self::Anonymous => formatter.write_str("Anonymous"),
+        }
+    }
+}
+
+impl AsyncClient {
+    /// Method for creating a new session.
+    /// The `server` and `school` parameter both depend on the school that the user is part of; You can get `server` from
+    /// [`School.server`](crate::School::server) and `school` from [`School.login_name`](crate::School::login_name).
+    ///
+    /// Use a [`ClientBuilder`] to configure timeouts, proxies and the like.
+    pub async fn login(
+        server: &str,
+        school: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<Self, Error> {
+        ClientBuilder::new()
+            .login_async(server, school, Credentials::password(username, password))
+            .await
+    }
+
+    /// Method for creating a new session using the app secret of a user, e.g. for accounts that log in via SSO and
+    /// have no password. The one-time password is computed locally from the secret.
+    pub async fn login_with_secret(
+        server: &str,
+        school: &str,
+        username: &str,
+        secret: &str,
+    ) -> Result<Self, Error> {
+        ClientBuilder::new()
+            .login_async(server, school, Credentials::secret(username, secret))
+            .await
+    }
+
+    /// Method for creating a new session from the QR code on a user's WebUntis profile page.
+    /// See [`SetupLink::parse()`] for turning the QR code's payload into a [`SetupLink`].
+    pub async fn login_with_setup_link(link: &SetupLink) -> Result<Self, Error> {
+        ClientBuilder::new()
+            .login_async(&link.server, &link.login_name, link.credentials())
+            .await
+    }
+
+    /// Method for creating a new anonymous session, for schools that publish their timetables to guests.
+    /// The resulting [`Session`] has no person attached, so the `own_timetable_*` methods can't be used.
+    pub async fn login_anonymous(server: &str, school: &str) -> Result<Self, Error> {
+        ClientBuilder::new()
+            .login_async(server, school, Credentials::Anonymous)
+            .await
+    }
+
+    /// Resumes a session from a [`SessionToken`] without authenticating again.
+    /// Fails with [`ErrorKind::InvalidSession`] if the server no longer accepts the session.
+    ///
+    /// Unlike new sessions, resumed sessions are not logged out when the client is dropped, so that the token stays
+    /// valid. Use [`logout()`](Self::logout) to end them.
+    pub async fn resume(token: SessionToken) -> Result<Self, Error> {
+        ClientBuilder::new().resume_async(token).await
+    }
+
+    pub(crate) async fn connect(
+        mut rpc_client: jsonrpc::Client,
+        endpoint: Endpoint,
+        credentials: Credentials,
+        keep_credentials: bool,
+    ) -> Result<Self, Error> {
+        let session = authenticate(&mut rpc_client, &endpoint, &credentials).await?;
+        Ok(Self {
+            rpc_client,
+            endpoint,
+            session,
+            credentials: Some(credentials).filter(|_| keep_credentials),
+            logout_on_drop: true,
+            lenient: false,
+            skipped: Vec::new(),
+            cache: None,
+        })
+    }
+
+    pub(crate) async fn reconnect(
+        mut rpc_client: jsonrpc::Client,
+        endpoint: Endpoint,
+        token: SessionToken,
+    ) -> Result<Self, Error> {
+        rpc_client.set_cookie(Some(token.cookie));
+
+        let check: Result<i64, Error> = rpc_client.request("getLatestImportTime", ()).await;
+        match check {
+            Err(err) if session_expired(&err) => return Err(ErrorKind::InvalidSession.into()),
+            Err(err) => return Err(err),
+            Ok(_) => {}
+        }
+
+        Ok(Self {
+            rpc_client,
+            endpoint,
+            session: token.session,
+            credentials: None,
+            logout_on_drop: false,
+            lenient: false,
+            skipped: Vec::new(),
+            cache: None,
+        })
+    }
+
+    /// Ends this client without logging out and returns a [`SessionToken`] that can be used to
+    /// [resume](Self::resume) the session later.
+    pub fn into_session_token(mut self) -> SessionToken {
+        self.logout_on_drop = false;
+        self.session_token()
+    }
+
+    pub(crate) fn session_token(&self) -> SessionToken {
+        SessionToken {
+            server: self.endpoint.server.clone(),
+            school: self.endpoint.school.clone(),
+            session: self.session.clone(),
+            cookie: self.rpc_client.cookie().unwrap_or_default().to_string(),
+        }
+    }
+
+    /// Returns the active session.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Sets whether the session is ended when this client is dropped, which is the default. The logout is handed to
+    /// the current tokio runtime, if there is one, and its result is ignored. Turn this off for sessions that are
+    /// persisted or shared elsewhere, and use [`logout()`](Self::logout) to end them explicitly.
+    pub fn set_logout_on_drop(&mut self, logout_on_drop: bool) {
+        self.logout_on_drop = logout_on_drop;
+    }
+
+    /// Sets whether malformed entries in lists, e.g. a lesson with a missing field, are skipped with a warning instead
+    /// of failing the whole request. The warnings are logged with [`tracing`](https://docs.rs/tracing), and the skipped
+    /// entries can be retrieved with [`take_skipped_entries()`](Self::take_skipped_entries). Off by default.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// Returns the entries that were skipped in lenient mode since the last call, and forgets them.
+    pub fn take_skipped_entries(&mut self) -> Vec<SkippedEntry> {
+        std::mem::take(&mut self.skipped)
+    }
+
+    /// Keeps the given credentials and uses them to authenticate again, whenever the server reports that the session
+    /// is no longer valid. The failed request is then retried once with the new session.
+    pub fn enable_reauthentication(&mut self, credentials: Credentials) {
+        self.credentials = Some(credentials);
+    }
+
+    /// Forgets the credentials passed to [`enable_reauthentication()`](Self::enable_reauthentication).
+    pub fn disable_reauthentication(&mut self) {
+        self.credentials = None;
+    }
+
+    /// Remembers master data and timetables, as configured by the [`CachePolicy`].
+    /// Replaces the previous cache, if there was one.
+    pub fn enable_cache(&mut self, policy: CachePolicy) {
+        self.cache = Some(Cache::new(policy));
+    }
+
+    /// Drops all cached responses and stops caching.
+    pub fn disable_cache(&mut self) {
+        self.cache = None;
+    }
+
+    /// Drops all cached responses, so that they are fetched again on the next request.
+    pub fn clear_cache(&mut self) {
+        if let Some(cache) = &mut self.cache {
+            cache.clear();
+        }
+    }
+
+    /// Returns the last time that any timetable at this school was updated.
+    pub async fn last_update_time(&mut self) -> Result<chrono::DateTime<chrono::Utc>, Error> {
+        let ts: i64 = self.request("getLatestImportTime", ()).await?;
+        chrono::Utc
+            .timestamp_millis_opt(ts)
+            .single()
+            .ok_or_else(|| {
+                let unexpected = serde::de::Unexpected::Signed(ts);
+                let expected = &"a timestamp in milliseconds";
+                Error::from(ErrorKind::Serde(serde::de::Error::invalid_value(
+                    unexpected, expected,
+                )))
+            })
+    }
+
+    /// Returns status data that can be used for displaying a timetable.
+    pub async fn status_data(&mut self) -> Result<StatusData, Error> {
+        self.request("getStatusData", ()).await
+    }
+
+    /// Retrieves the current schoolyear.
+    pub async fn current_schoolyear(&mut self) -> Result<Schoolyear, Error> {
+        self.request("getCurrentSchoolyear", ()).await
+    }
+
+    /// Retrieves a list of all schoolyears.
+    pub async fn schoolyears(&mut self) -> Result<Vec<Schoolyear>, Error> {
+        self.request_list("getSchoolyears", ()).await
+    }
+
+    /// Retrieves the holidays in the current schoolyear.
+    pub async fn holidays(&mut self) -> Result<Vec<Holiday>, Error> {
+        self.request_list("getHolidays", ()).await
+    }
+
+    /// Retrieves the list of rooms in the user's school.
+    pub async fn rooms(&mut self) -> Result<Vec<Room>, Error> {
+        self.request_list("getRooms", ()).await
+    }
+
+    /// Retrieves the list of classes in the user's school.
+    pub async fn classes(&mut self) -> Result<Vec<Class>, Error> {
+        self.request_list("getKlassen", ()).await
+    }
+
+    /// Retrieves the list of subjects in the user's school.
+    pub async fn subjects(&mut self) -> Result<Vec<Subject>, Error> {
+        self.request_list("getSubjects", ()).await
+    }
+
+    /// Retrieves the list of teachers in the user's school.
+    pub async fn teachers(&mut self) -> Result<Vec<Teacher>, Error> {
+        self.request_list("getTeachers", ()).await
+    }
+
+    /// Retrieves the list of students in the user's school.
+    pub async fn students(&mut self) -> Result<Vec<Student>, Error> {
+        self.request_list("getStudents", ()).await
+    }
+
+    /// Retrieves the user's own timetable between now and a given date.
+    pub async fn own_timetable_until(&mut self, end_date: &Date) -> Result<Vec<Lesson>, Error> {
+        self.own_timetable_between(&Date::today(), end_date).await
+    }
+
+    /// Retrieves the users's own timetable for the current week.
+    pub async fn own_timetable_current_week(&mut self) -> Result<Vec<Lesson>, Error> {
+        self.own_timetable_for_week(&Date::today()).await
+    }
+
+    /// Retrieves the users's own timetable for the week that a given date is in.
+    pub async fn own_timetable_for_week(&mut self, date: &Date) -> Result<Vec<Lesson>, Error> {
+        self.own_timetable_between(&date.relative_week_begin(), &date.relative_week_end())
+            .await
+    }
+
+    /// Retrieves the users's own timetable between two dates.
+    pub async fn own_timetable_between(
+        &mut self,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        if self.session.is_anonymous() {
+            return Err(ErrorKind::Anonymous.into());
+        }
+        let (id, ty) = match (self.session.person_id, &self.session.person_type) {
+            (Some(id), Some(ty)) => (id, ty.clone()),
+            _ => return Err(ErrorKind::UnknownPersonType.into()),
+        };
+        self.timetable_between(&id, &ty, start_date, end_date).await
+    }
+
+    /// Retrieves an element's timetable between now and a given date.
+    pub async fn timetable_until(
+        &mut self,
+        id: &usize,
+        ty: &ElementType,
+        end_date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        self.timetable_between(id, ty, &Date::today(), end_date)
+            .await
+    }
+
+    /// Retrieves an element's timetable for the current week.
+    pub async fn timetable_current_week(
+        &mut self,
+        id: &usize,
+        ty: &ElementType,
+    ) -> Result<Vec<Lesson>, Error> {
+        self.timetable_for_week(id, ty, &Date::today()).await
+    }
+
+    /// Retrieves an element's timetable for the week that a given date is in.
+    pub async fn timetable_for_week(
+        &mut self,
+        id: &usize,
+        ty: &ElementType,
+        date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        self.timetable_between(
+            id,
+            ty,
+            &date.relative_week_begin(),
+            &date.relative_week_end(),
+        )
+        .await
+    }
+
+    /// Retrieves an element's own timetable between two dates.
+    pub async fn timetable_between(
+        &mut self,
+        id: &usize,
+        ty: &ElementType,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        let params = params::TimetableParams::new(id, ty, start_date, end_date);
+        self.request_list("getTimetable", params).await
+    }
+
+    /// Retrieves the list of departments in the user's school.
+    pub async fn departments(&mut self) -> Result<Vec<Department>, Error> {
+        self.request_list("getDepartments", ()).await
+    }
+
+    /// Retrieves the changes to the timetable between two dates for all classes of a department.
+    /// Use `0` as `department_id` to get the changes for the whole school.
+    pub async fn substitutions(
+        &mut self,
+        start_date: &Date,
+        end_date: &Date,
+        department_id: &usize,
+    ) -> Result<Vec<Substitution>, Error> {
+        let params = params::SubstitutionsParams {
+            start_date,
+            end_date,
+            department_id,
+        };
+        self.request_list("getSubstitutions", params).await
+    }
+
+    /// Retrieves the list of exam types in the user's school.
+    pub async fn exam_types(&mut self) -> Result<Vec<ExamType>, Error> {
+        self.request_list("getExamTypes", ()).await
+    }
+
+    /// Retrieves the exams of a type between two dates.
+    pub async fn exams(
+        &mut self,
+        exam_type_id: &usize,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<Exam>, Error> {
+        let params = params::ExamsParams {
+            exam_type_id,
+            start_date,
+            end_date,
+        };
+        self.request_list("getExams", params).await
+    }
+
+    /// Retrieves the class register entries between two dates.
+    pub async fn classreg_events(
+        &mut self,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<ClassregEvent>, Error> {
+        let params = params::ClassregEventsParams {
+            start_date,
+            end_date,
+            element: None,
+        };
+        self.request_list("getClassregEvents", params).await
+    }
+
+    /// Retrieves the class register entries of a class or student between two dates.
+    pub async fn classreg_events_for(
+        &mut self,
+        id: &usize,
+        ty: &ElementType,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<ClassregEvent>, Error> {
+        let params = params::ClassregEventsParams {
+            start_date,
+            end_date,
+            element: Some(params::TimetableParamsElem { id, ty }),
+        };
+        self.request_list("getClassregEvents", params).await
+    }
+
+    /// Retrieves the list of class register categories.
+    pub async fn classreg_categories(&mut self) -> Result<Vec<ClassregCategory>, Error> {
+        self.request_list("getClassregCategories", ()).await
+    }
+
+    /// Retrieves the list of class register category groups.
+    pub async fn classreg_category_groups(&mut self) -> Result<Vec<ClassregCategoryGroup>, Error> {
+        self.request_list("getClassregCategoryGroups", ()).await
+    }
+
+    /// Retrieves the lessons between two dates, together with the absences of students in them.
+    pub async fn timetable_with_absences(
+        &mut self,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<LessonWithAbsences>, Error> {
+        let method = "getTimetableWithAbsences";
+        let params = params::TimetableWithAbsencesParams::new(start_date, end_date);
+        if !self.lenient {
+            let result: PeriodsWithAbsences<LessonWithAbsences> =
+                self.request(method, params).await?;
+            return Ok(result.periods_with_absences);
+        }
+
+        let result: PeriodsWithAbsences<serde_json::Value> = self.request(method, params).await?;
+        Ok(self.skip_malformed(method, result.periods_with_absences))
+    }
+
+    /// Retrieves the timegrid of the user's school.
+    pub async fn timegrid(&mut self) -> Result<Timegrid, Error> {
+        self.request("getTimegridUnits", ()).await
+    }
+
+    /// Calls a JSON-RPC method that has no wrapper in this crate, using this client's session.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # async fn run(client: &mut untis::AsyncClient) -> Result<(), untis::Error> {
+    /// let periods: Vec<serde_json::Value> = client.call("getTimegridUnits", ()).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn call<T: DeserializeOwned, P: Serialize>(
+        &mut self,
+        method: &str,
+        params: P,
+    ) -> Result<T, Error> {
+        self.request(method, params).await
+    }
+
+    /// Calls a JSON-RPC method that has no wrapper in this crate and returns the raw result.
+    /// See [`call()`](Self::call).
+    pub async fn call_value<P: Serialize>(
+        &mut self,
+        method: &str,
+        params: P,
+    ) -> Result<serde_json::Value, Error> {
+        self.request(method, params).await
+    }
+
+    async fn request_list<T: DeserializeOwned, P: Serialize>(
+        &mut self,
+        method: &str,
+        params: P,
+    ) -> Result<Vec<T>, Error> {
+        if !self.lenient {
+            return self.request(method, params).await;
+        }
+
+        let entries = self.request(method, params).await?;
+        Ok(self.skip_malformed(method, entries))
+    }
+
+    /// Parses the entries of a list, skipping and remembering those that are malformed.
+    fn skip_malformed<T: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        entries: Vec<serde_json::Value>,
+    ) -> Vec<T> {
+        let mut parsed = Vec::with_capacity(entries.len());
+        for entry in entries {
+            match T::deserialize(&entry) {
+                Ok(value) => parsed.push(value),
+                Err(err) => {
+                    tracing::warn!(method, error = %err, "skipping malformed entry");
+                    self.skipped.push(SkippedEntry {
+                        method: method.to_string(),
+                        entry,
+                        error: err.to_string(),
+                    });
+                }
+            }
+        }
+        parsed
+    }
+
+    async fn request<T: DeserializeOwned, P: Serialize>(
+        &mut self,
+        method: &str,
+        params: P,
+    ) -> Result<T, Error> {
+        if self.cache.is_none() || !cache::CACHED_METHODS.contains(&method) {
+            return self.send_request(method, params).await;
+        }
+
+        self.revalidate_cache().await?;
+        let key = Cache::key(method, &serde_json::to_value(&params)?);
+        if let Some(value) = self.cache.as_ref().and_then(|cache| cache.get(&key)) {
+            return Ok(T::deserialize(value)?);
+        }
+
+        let value: serde_json::Value = self.send_request(method, params).await?;
+        let result = T::deserialize(&value)?;
+        if let Some(cache) = &mut self.cache {
+            cache.insert(key, value);
+        }
+        Ok(result)
+    }
+
+    async fn revalidate_cache(&mut self) -> Result<(), Error> {
+        if !self.cache.as_ref().is_some_and(Cache::needs_revalidation) {
+            return Ok(());
+        }
+        let import_time = self.send_request("getLatestImportTime", ()).await?;
+        if let Some(cache) = &mut self.cache {
+            cache.revalidate(import_time);
+        }
+        Ok(())
+    }
+
+    /// Sends a request without looking at the cache.
+    async fn send_request<T: DeserializeOwned, P: Serialize>(
+        &mut self,
+        method: &str,
+        params: P,
+    ) -> Result<T, Error> {
+        let result = self.rpc_client.request(method, &params).await;
+        if !matches!(&result, Err(err) if session_expired(err)) || !self.reauthenticate().await? {
+            return result;
+        }
+        self.rpc_client.request(method, &params).await
+    }
+
+    /// Sends all calls of a [`Batch`] in a single request. An empty batch is not sent at all.
+    pub async fn send_batch(&mut self, batch: Batch) -> Result<BatchResults, Error> {
+        let calls = batch.into_calls()?;
+        let mut results = self.rpc_client.request_batch(&calls).await;
+        let expired = match &results {
+            Ok(results) => results
+                .iter()
+                .any(|r| matches!(r, Err(err) if session_expired(err))),
+            Err(err) => session_expired(err),
+        };
+        if expired && self.reauthenticate().await? {
+            results = self.rpc_client.request_batch(&calls).await;
+        }
+        Ok(BatchResults::new(results?))
+    }
+
+    /// Authenticates again, if re-authentication is enabled. Returns whether a new session was created.
+    async fn reauthenticate(&mut self) -> Result<bool, Error> {
+        let Some(credentials) = self.credentials.clone() else {
+            return Ok(false);
+        };
+        tracing::info!("session expired, authenticating again");
+        self.session = authenticate(&mut self.rpc_client, &self.endpoint, &credentials).await?;
+        Ok(true)
+    }
+
+    /// Ends the session on the server. Unlike the logout on drop, this reports whether the logout succeeded.
+    pub async fn logout(mut self) -> Result<(), Error> {
+        self.end_session().await
+    }
+
+    pub(crate) async fn end_session(&mut self) -> Result<(), Error> {
+        self.logout_on_drop = false;
+        self.rpc_client.request("logout", ()).await
+    }
+}
+
+impl Drop for AsyncClient {
+    fn drop(&mut self) {
+        if !self.logout_on_drop || std::thread::panicking() {
+            return;
+        }
+
+        // The logout can't be awaited here, so it is handed to the current runtime, if there is one.
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let mut rpc_client = self.rpc_client.clone();
+            handle.spawn(async move {
+                _ = rpc_client.request::<(), _>("logout", ()).await;
+            });
+        }
+    }
+}
+
+impl School {
+    pub async fn client_login_async(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<AsyncClient, Error> {
+        AsyncClient::login(&self.server, &self.login_name, username, password).await
+    }
+
+    pub async fn client_login_with_secret_async(
+        &self,
+        username: &str,
+        secret: &str,
+    ) -> Result<AsyncClient, Error> {
+        AsyncClient::login_with_secret(&self.server, &self.login_name, username, secret).await
+    }
+
+    pub async fn client_login_anonymous_async(&self) -> Result<AsyncClient, Error> {
+        AsyncClient::login_anonymous(&self.server, &self.login_name).await
+    }
+}
+
+/// Describes where a school's Untis instance can be reached.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub(crate) struct Endpoint {
+    pub server: String,
+    pub school: String,
+    /// Scheme and host that requests are sent to, e.g. `https://ajax.webuntis.com`.
+    pub base_url: String,
+    /// Name that this client identifies itself with when authenticating.
+    pub client_name: String,
+}
+
+impl Endpoint {
+    pub fn jsonrpc_url(&self) -> String {
+        format!(
+            "{}/WebUntis/jsonrpc.do?school={}",
+            self.base_url, self.school
+        )
+    }
+
+    pub fn intern_url(&self, method: &str) -> String {
+        format!(
+            "{}/WebUntis/jsonrpc_intern.do?m={}&school={}&v=i2.2",
+            self.base_url, method, self.school
+        )
+    }
+}
+
+fn session_expired(err: &Error) -> bool {
+    err.rpc_code() == Some(jsonrpc::ErrorCode::NotAuthenticated)
+}
+
+async fn authenticate(
+    rpc_client: &mut jsonrpc::Client,
+    endpoint: &Endpoint,
+    credentials: &Credentials,
+) -> Result<Session, Error> {
+    rpc_client.set_cookie(None);
+    let session: Session = match credentials {
+        Credentials::Password { username, password } => {
+            let params = params::AuthenticateParams {
+                client: &endpoint.client_name,
+                user: username,
+                password,
+            };
+            rpc_client.request("authenticate", params).await?
+        }
+        Credentials::Anonymous => {
+            // Fails if the school doesn't allow anonymous access.
+            let mut intern_client = rpc_client.with_url(&endpoint.intern_url("getAppSharedSecret"));
+            let params = params::AppSharedSecretParams {
+                user_name: ANONYMOUS_USER,
+                password: "",
+            };
+            let _: serde_json::Value = intern_client
+                .request("getAppSharedSecret", vec![params])
+                .await?;
+
+            let time = chrono::Utc::now().timestamp_millis();
+            let mut session =
+                otp_login(rpc_client, endpoint, ANONYMOUS_USER, ANONYMOUS_OTP, time).await?;
+            session.person_id = None;
+            session.anonymous = true;
+            session
+        }
+        Credentials::Secret { username, secret } => {
+            let time = chrono::Utc::now().timestamp_millis();
+            let otp = otp::totp(secret, time)?;
+            otp_login(rpc_client, endpoint, username, otp, time).await?
+        }
+    };
+    rpc_client.set_cookie(Some(make_session_cookie(&session)));
+    Ok(session)
+}
+
+/// The user name that WebUntis uses for guest access.
+const ANONYMOUS_USER: &str = "#anonymous#";
+
+/// Guest access uses a fixed one-time password.
+const ANONYMOUS_OTP: u32 = 100170;
+
+/// Logs in against the internal API used by the mobile app. Instead of returning a session, the server only sets
+/// the session cookie.
+async fn otp_login(
+    rpc_client: &mut jsonrpc::Client,
+    endpoint: &Endpoint,
+    user: &str,
+    otp: u32,
+    client_time: i64,
+) -> Result<Session, Error> {
+    let mut intern_client = rpc_client.with_url(&endpoint.intern_url("getUserData2017"));
+    let params = params::UserDataParams {
+        auth: params::OtpAuthParams {
+            client_time,
+            user,
+            otp,
+        },
+    };
+    let result: UserDataResult = intern_client
+        .request("getUserData2017", vec![params])
+        .await?;
+    let user_data = result.user_data;
+
+    let session_id = intern_client
+        .cookie()
+        .and_then(|cookie| cookie.strip_prefix("JSESSIONID="))
+        .ok_or(ErrorKind::InvalidSession)?;
+    let person_type = user_data
+        .elem_type
+        .as_deref()
+        .and_then(element_type_from_name);
+    Ok(Session {
+        session_id: session_id.to_string(),
+        class_id: user_data.klassen_ids.first().copied(),
+        person_id: user_data.elem_id,
+        person_type,
+        anonymous: false,
+        extra: Extra::default(),
+    })
+}
+
+fn element_type_from_name(name: &str) -> Option<ElementType> {
+    match name {
+        "CLASS" => Some(ElementType::Class),
+        "TEACHER" => Some(ElementType::Teacher),
+        "SUBJECT" => Some(ElementType::Subject),
+        "ROOM" => Some(ElementType::Room),
+        "STUDENT" => Some(ElementType::Student),
+        _ => None,
+    }
+}
+
+fn make_session_cookie(session: &Session) -> String {
+    format!("JSESSIONID={}", session.session_id)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::jsonrpc::{Transport, TransportFuture, TransportRequest, TransportResponse};
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    type Handler = dyn Fn(&TransportRequest, &str) -> String + Send + Sync;
+
+    /// Answers requests with a closure and records them.
+    #[derive(Clone)]
+    pub(crate) struct FakeTransport {
+        handler: Arc<Handler>,
+        requests: Arc<Mutex<Vec<TransportRequest>>>,
+        session_cookie: Option<String>,
+    }
+
+    impl FakeTransport {
+        pub(crate) fn new(
+            handler: impl Fn(&TransportRequest, &str) -> String + Send + Sync + 'static,
+        ) -> Self {
+            Self {
+                handler: Arc::new(handler),
+                requests: Arc::default(),
+                session_cookie: None,
+            }
+        }
+
+        /// Sets a session cookie on every response.
+        fn with_session_cookie(mut self, cookie: &str) -> Self {
+            self.session_cookie = Some(cookie.to_string());
+            self
+        }
+
+        fn urls(&self) -> Vec<String> {
+            let requests = self.requests.lock().unwrap();
+            requests.iter().map(|r| r.url.clone()).collect()
+        }
+
+        pub(crate) fn methods(&self) -> Vec<String> {
+            let requests = self.requests.lock().unwrap();
+            requests.iter().map(|r| method_of(r).to_string()).collect()
+        }
+    }
+
+    impl Transport for FakeTransport {
+        fn send(&self, request: TransportRequest) -> TransportFuture<'_> {
+            let body = (self.handler)(&request, &method_of(&request));
+            self.requests.lock().unwrap().push(request);
+            let session_cookie = self.session_cookie.clone();
+            Box::pin(async move {
+                Ok(TransportResponse {
+                    status: reqwest::StatusCode::OK,
+                    session_cookie,
+                    body,
+                })
+            })
+        }
+    }
+
+    fn method_of(request: &TransportRequest) -> String {
+        let value: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        match value {
+            serde_json::Value::Array(calls) => {
+                let methods: Vec<_> = calls.iter().filter_map(|c| c["method"].as_str()).collect();
+                methods.join(",")
+            }
+            call => call["method"].as_str().unwrap().to_string(),
+        }
+    }
+
+    pub(crate) fn result(value: &str) -> String {
+        format!(r#"{{"jsonrpc":"2.0","id":"1","result":{}}}"#, value)
+    }
+
+    pub(crate) fn error(code: isize) -> String {
+        format!(
+            r#"{{"jsonrpc":"2.0","id":"1","error":{{"code":{},"message":"error"}}}}"#,
+            code
+        )
+    }
+
+    pub(crate) fn session(id: &str) -> String {
+        result(&format!(
+            r#"{{"sessionId":"{}","personType":5,"personId":7,"klasseId":3}}"#,
+            id
+        ))
+    }
+
+    async fn login(transport: FakeTransport) -> AsyncClient {
+        login_with(ClientBuilder::new(), transport).await
+    }
+
+    async fn login_with(builder: ClientBuilder, transport: FakeTransport) -> AsyncClient {
+        builder
+            .transport(transport)
+            .logout_on_drop(false)
+            .login_async("server", "school", Credentials::password("user", "pw"))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn login_uses_session_cookie() {
+        let transport = FakeTransport::new(|request, method| match method {
+            "authenticate" => {
+                assert!(request.body.contains(r#""client":"test-client""#));
+                session("abc")
+            }
+            _ => {
+                assert_eq!(request.cookie.as_deref(), Some("JSESSIONID=abc"));
+                result("1700000000000")
+            }
+        });
+
+        let mut client = login_with(
+            ClientBuilder::new().client_name("test-client"),
+            transport.clone(),
+        )
+        .await;
+        client.last_update_time().await.unwrap();
+
+        assert_eq!(client.session().person_id, Some(7));
+        assert_eq!(transport.methods(), ["authenticate", "getLatestImportTime"]);
+    }
+
+    fn session_token() -> SessionToken {
+        SessionToken {
+            server: String::from("server"),
+            school: String::from("school"),
+            session: serde_json::from_str(r#"{"sessionId":"abc","personType":5,"personId":7}"#)
+                .unwrap(),
+            cookie: String::from("JSESSIONID=abc"),
+        }
+    }
+
+    async fn resume(transport: FakeTransport) -> Result<AsyncClient, Error> {
+        ClientBuilder::new()
+            .transport(transport)
+            .resume_async(session_token())
+            .await
+    }
+
+    /// Lets the runtime run the logout that a dropped client may have spawned.
+    async fn settle() {
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn resumes_session_with_cookie() {
+        let transport = FakeTransport::new(|request, method| {
+            assert_eq!(request.cookie.as_deref(), Some("JSESSIONID=abc"));
+            match method {
+                "getLatestImportTime" => result("1700000000000"),
+                _ => result("[]"),
+            }
+        });
+
+        let mut client = resume(transport.clone()).await.unwrap();
+        client.rooms().await.unwrap();
+
+        assert_eq!(client.session().person_id, Some(7));
+        assert_eq!(client.session_token(), session_token());
+        assert_eq!(transport.methods(), ["getLatestImportTime", "getRooms"]);
+    }
+
+    #[tokio::test]
+    async fn dropping_resumed_client_keeps_session() {
+        let transport = FakeTransport::new(|_, method| match method {
+            "getLatestImportTime" => result("1700000000000"),
+            _ => result("null"),
+        });
+
+        drop(resume(transport.clone()).await.unwrap());
+        settle().await;
+
+        assert_eq!(transport.methods(), ["getLatestImportTime"]);
+    }
+
+    #[tokio::test]
+    async fn resuming_expired_session_fails() {
+        let transport =
+            FakeTransport::new(|_, _| error(jsonrpc::ErrorCode::NotAuthenticated.as_isize()));
+        let result = resume(transport).await;
+        assert!(matches!(
+            result.err().unwrap().kind(),
+            ErrorKind::InvalidSession
+        ));
+
+        let transport = FakeTransport::new(|_, _| error(jsonrpc::ErrorCode::NoAccess.as_isize()));
+        let error = resume(transport).await.err().unwrap();
+        assert_eq!(error.rpc_code(), Some(jsonrpc::ErrorCode::NoAccess));
+    }
+
+    #[tokio::test]
+    async fn anonymous_login_uses_internal_api() {
+        let transport = FakeTransport::new(|request, method| {
+            let params =
+                &serde_json::from_str::<serde_json::Value>(&request.body).unwrap()["params"];
+            match method {
+                "getAppSharedSecret" => {
+                    assert_eq!(params[0]["userName"], ANONYMOUS_USER);
+                    result(r#""""#)
+                }
+                "getUserData2017" => {
+                    assert_eq!(params[0]["auth"]["user"], ANONYMOUS_USER);
+                    assert_eq!(params[0]["auth"]["otp"], ANONYMOUS_OTP);
+                    result(r#"{"userData":{"elemType":null,"elemId":0,"klassenIds":[]}}"#)
+                }
+                _ => {
+                    assert_eq!(request.cookie.as_deref(), Some("JSESSIONID=guest"));
+                    result("[]")
+                }
+            }
+        })
+        .with_session_cookie("JSESSIONID=guest");
+
+        let mut client = ClientBuilder::new()
+            .transport(transport.clone())
+            .base_url("https://example.com")
+            .logout_on_drop(false)
+            .login_async("server", "school", Credentials::Anonymous)
+            .await
+            .unwrap();
+        client.rooms().await.unwrap();
+        let date = Date::today();
+        let timetable = client.own_timetable_between(&date, &date).await;
+
+        let session = client.session();
+        assert_eq!(session.session_id, "guest");
+        assert!(session.is_anonymous());
+        assert_eq!(session.person_id, None);
+        assert_eq!(session.person_type, None);
+        assert_eq!(session.class_id, None);
+        assert!(matches!(
+            timetable.unwrap_err().kind(),
+            ErrorKind::Anonymous
+        ));
+        assert_eq!(
+            transport.urls(),
+            [
+                "https://example.com/WebUntis/jsonrpc_intern.do?m=getAppSharedSecret&school=school&v=i2.2",
+                "https://example.com/WebUntis/jsonrpc_intern.do?m=getUserData2017&school=school&v=i2.2",
+                "https://example.com/WebUntis/jsonrpc.do?school=school",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn secret_login_sends_one_time_password() {
+        const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+        let transport = FakeTransport::new(|request, method| {
+            assert_eq!(method, "getUserData2017");
+            assert!(request
+                .url
+                .contains("/WebUntis/jsonrpc_intern.do?m=getUserData2017&school=school"));
+            let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+            let auth = &body["params"][0]["auth"];
+            let otp = otp::totp(SECRET, auth["clientTime"].as_i64().unwrap()).unwrap();
+            assert_eq!(auth["user"], "user");
+            assert_eq!(auth["otp"], otp);
+            result(r#"{"userData":{"elemType":"STUDENT","elemId":7,"klassenIds":[3,4]}}"#)
+        })
+        .with_session_cookie("JSESSIONID=abc");
+
+        let client = ClientBuilder::new()
+            .transport(transport)
+            .logout_on_drop(false)
+            .login_async("server", "school", Credentials::secret("user", SECRET))
+            .await
+            .unwrap();
+
+        let session = client.session();
+        assert_eq!(session.session_id, "abc");
+        assert!(!session.is_anonymous());
+        assert_eq!(session.person_id, Some(7));
+        assert_eq!(session.person_type, Some(ElementType::Student));
+        assert_eq!(session.class_id, Some(3));
+    }
+
+    #[tokio::test]
+    async fn secret_login_keeps_persons_of_unknown_type() {
+        let transport = FakeTransport::new(|_, _| {
+            result(r#"{"userData":{"elemType":"LEGAL_GUARDIAN","elemId":9,"klassenIds":[]}}"#)
+        })
+        .with_session_cookie("JSESSIONID=abc");
+
+        let mut client = ClientBuilder::new()
+            .transport(transport)
+            .logout_on_drop(false)
+            .login_async("server", "school", Credentials::secret("user", "GEZDGNBV"))
+            .await
+            .unwrap();
+        let date = Date::today();
+        let timetable = client.own_timetable_between(&date, &date).await;
+
+        let session = client.session();
+        assert!(!session.is_anonymous());
+        assert_eq!(session.person_id, Some(9));
+        assert_eq!(session.person_type, None);
+        assert!(matches!(
+            timetable.unwrap_err().kind(),
+            ErrorKind::UnknownPersonType
+        ));
+    }
+
+    #[tokio::test]
+    async fn secret_login_needs_session_cookie() {
+        let transport = FakeTransport::new(|_, _| {
+            result(r#"{"userData":{"elemType":"TEACHER","elemId":7,"klassenIds":[]}}"#)
+        });
+
+        let result = ClientBuilder::new()
+            .transport(transport)
+            .logout_on_drop(false)
+            .login_async("server", "school", Credentials::secret("user", "GEZDGNBV"))
+            .await;
+        assert!(matches!(
+            result.err().unwrap().kind(),
+            ErrorKind::InvalidSession
+        ));
+
+        let result = ClientBuilder::new()
+            .transport(FakeTransport::new(|_, _| unreachable!()))
+            .login_async(
+                "server",
+                "school",
+                Credentials::secret("user", "not base32!"),
+            )
+            .await;
+        assert!(matches!(
+            result.err().unwrap().kind(),
+            ErrorKind::InvalidSecret
+        ));
+    }
+
+    #[tokio::test]
+    async fn anonymous_login_fails_without_guest_access() {
+        let transport = FakeTransport::new(|_, _| error(jsonrpc::ErrorCode::NoAccess.as_isize()));
+
+        let result = ClientBuilder::new()
+            .transport(transport.clone())
+            .logout_on_drop(false)
+            .login_async("server", "school", Credentials::Anonymous)
+            .await;
+
+        assert!(result.err().unwrap().is_permission_error());
+        assert_eq!(transport.methods(), ["getAppSharedSecret"]);
+    }
+
+    fn logout_transport(logout: String) -> FakeTransport {
+        FakeTransport::new(move |_, method| match method {
+            "authenticate" => session("abc"),
+            _ => logout.clone(),
+        })
+    }
+
+    async fn login_logging_out_on_drop(transport: FakeTransport) -> AsyncClient {
+        ClientBuilder::new()
+            .transport(transport)
+            .login_async("server", "school", Credentials::password("user", "pw"))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn logout_ends_session_once() {
+        let transport = logout_transport(result("null"));
+        let client = login_logging_out_on_drop(transport.clone()).await;
+        client.logout().await.unwrap();
+        settle().await;
+        assert_eq!(transport.methods(), ["authenticate", "logout"]);
+
+        let transport = logout_transport(error(jsonrpc::ErrorCode::NotAuthenticated.as_isize()));
+        let client = login_logging_out_on_drop(transport.clone()).await;
+        let error = client.logout().await.unwrap_err();
+        settle().await;
+        assert_eq!(error.rpc_code(), Some(jsonrpc::ErrorCode::NotAuthenticated));
+        assert_eq!(error.method(), Some("logout"));
+        assert_eq!(transport.methods(), ["authenticate", "logout"]);
+    }
+
+    #[tokio::test]
+    async fn drop_logs_out_unless_disabled() {
+        let transport = logout_transport(result("null"));
+        drop(login_logging_out_on_drop(transport.clone()).await);
+        settle().await;
+        assert_eq!(transport.methods(), ["authenticate", "logout"]);
+
+        let transport = logout_transport(result("null"));
+        let mut client = login_logging_out_on_drop(transport.clone()).await;
+        client.set_logout_on_drop(false);
+        drop(client);
+        let client = login_logging_out_on_drop(transport.clone()).await;
+        let token = client.into_session_token();
+        settle().await;
+        assert_eq!(token.cookie, "JSESSIONID=abc");
+        assert_eq!(transport.methods(), ["authenticate", "authenticate"]);
+    }
+
+    #[tokio::test]
+    async fn reauthenticates_once_when_session_expired() {
+        let logins = Arc::new(Mutex::new(0));
+        let transport = FakeTransport::new({
+            let logins = logins.clone();
+            move |request, method| match method {
+                "authenticate" => {
+                    let mut logins = logins.lock().unwrap();
+                    *logins += 1;
+                    session(&format!("session{}", logins))
+                }
+                _ if request.cookie.as_deref() == Some("JSESSIONID=session1") => {
+                    error(jsonrpc::ErrorCode::NotAuthenticated.as_isize())
+                }
+                _ => result("[]"),
+            }
+        });
+
+        let mut client =
+            login_with(ClientBuilder::new().reauthenticate(true), transport.clone()).await;
+        let rooms = client.rooms().await.unwrap();
+
+        assert!(rooms.is_empty());
+        assert_eq!(client.session().session_id, "session2");
+        assert_eq!(
+            transport.methods(),
+            ["authenticate", "getRooms", "authenticate", "getRooms"]
+        );
+    }
+
+    #[tokio::test]
+    async fn batch_results_are_matched_by_id() {
+        let transport = FakeTransport::new(|_, method| match method {
+            "authenticate" => session("abc"),
+            _ => String::from(
+                r#"[
+                    {"jsonrpc":"2.0","id":3,"error":{"code":-8509,"message":"no right"}},
+                    {"jsonrpc":"2.0","id":"2","result":[]}
+                ]"#,
+            ),
+        });
+
+        let mut client = login(transport.clone()).await;
+
+        let mut batch = Batch::new();
+        let rooms = batch.rooms();
+        let teachers = batch.teachers();
+        let mut results = client.send_batch(batch).await.unwrap();
+
+        assert!(results.get(rooms).unwrap().is_empty());
+        let error = results.get(teachers).unwrap_err();
+        assert_eq!(error.rpc_code(), Some(jsonrpc::ErrorCode::NoAccess));
+        assert_eq!(error.method(), Some("getTeachers"));
+        assert_eq!(error.request_id(), Some("3"));
+        assert_eq!(
+            error.to_string(),
+            "getTeachers (request 3) failed: RPC Error: -8509 no right"
+        );
+        assert_eq!(
+            transport.methods(),
+            ["authenticate", "getRooms,getTeachers"]
+        );
+    }
+
+    #[tokio::test]
+    async fn batch_can_be_rejected_as_a_whole() {
+        let transport = FakeTransport::new(|_, method| match method {
+            "authenticate" => session("abc"),
+            _ => error(jsonrpc::ErrorCode::InvalidRequest.as_isize()),
+        });
+
+        let mut client = login(transport.clone()).await;
+        client.send_batch(Batch::new()).await.unwrap();
+        let mut batch = Batch::new();
+        batch.rooms();
+        batch.teachers();
+        let error = client.send_batch(batch).await.unwrap_err();
+
+        assert_eq!(error.rpc_code(), Some(jsonrpc::ErrorCode::InvalidRequest));
+        assert_eq!(error.method(), Some("getRooms,getTeachers"));
+        assert_eq!(
+            transport.methods(),
+            ["authenticate", "getRooms,getTeachers"]
+        );
+    }
+
+    #[tokio::test]
+    async fn calls_unwrapped_methods() {
+        let transport = FakeTransport::new(|request, method| match method {
+            "authenticate" => session("abc"),
+            _ => {
+                assert!(request.body.contains(r#""params":{"a":1}"#));
+                result(r#"{"custom":true}"#)
+            }
+        });
+
+        let mut client = login(transport.clone()).await;
+        let params = serde_json::json!({"a": 1});
+        let value = client.call_value("getCustom", &params).await.unwrap();
+
+        assert_eq!(value, serde_json::json!({"custom": true}));
+        assert_eq!(transport.methods(), ["authenticate", "getCustom"]);
+    }
+
+    #[tokio::test]
+    async fn exams_are_requested_by_type_and_dates() {
+        let transport = FakeTransport::new(|request, method| match method {
+            "authenticate" => session("abc"),
+            _ => {
+                let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+                assert_eq!(
+                    body["params"],
+                    serde_json::json!({"examTypeId": 2, "startDate": 20240304, "endDate": 20240308})
+                );
+                result("[]")
+            }
+        });
+
+        let mut client = login(transport.clone()).await;
+        let start = Date(chrono::NaiveDate::from_ymd_opt(2024, 3, 4).unwrap());
+        let end = Date(chrono::NaiveDate::from_ymd_opt(2024, 3, 8).unwrap());
+        let exams = client.exams(&2, &start, &end).await.unwrap();
+
+        assert!(exams.is_empty());
+        assert_eq!(transport.methods(), ["authenticate", "getExams"]);
+    }
+
+    #[tokio::test]
+    async fn classreg_events_can_be_requested_by_element() {
+        let params = Arc::new(Mutex::new(Vec::new()));
+        let transport = FakeTransport::new({
+            let params = params.clone();
+            move |request, method| match method {
+                "authenticate" => session("abc"),
+                _ => {
+                    let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+                    params.lock().unwrap().push(body["params"].clone());
+                    result("[]")
+                }
+            }
+        });
+
+        let mut client = login(transport).await;
+        let start = Date(chrono::NaiveDate::from_ymd_opt(2024, 3, 4).unwrap());
+        let end = Date(chrono::NaiveDate::from_ymd_opt(2024, 3, 8).unwrap());
+        client.classreg_events(&start, &end).await.unwrap();
+        client
+            .classreg_events_for(&7, &ElementType::Student, &start, &end)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *params.lock().unwrap(),
+            [
+                serde_json::json!({"startDate": 20240304, "endDate": 20240308}),
+                serde_json::json!({
+                    "startDate": 20240304,
+                    "endDate": 20240308,
+                    "element": {"id": 7, "type": 5}
+                }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn lenient_mode_skips_malformed_entries() {
+        let transport = FakeTransport::new(|_, method| match method {
+            "authenticate" => session("abc"),
+            _ => result(
+                r#"[
+                    {"id":1,"name":"R1","longName":"Room 1","active":true,"building":"","floor":2},
+                    {"id":2,"name":"R2"}
+                ]"#,
+            ),
+        });
+
+        let mut client = login(transport).await;
+        assert!(client.rooms().await.is_err());
+
+        client.set_lenient(true);
+        let rooms = client.rooms().await.unwrap();
+        assert_eq!(rooms.len(), 1);
+        assert_eq!(rooms[0].extra.get("floor"), Some(&serde_json::json!(2)));
+
+        let skipped = client.take_skipped_entries();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].method, "getRooms");
+        assert_eq!(skipped[0].entry, serde_json::json!({"id": 2, "name": "R2"}));
+        assert!(client.take_skipped_entries().is_empty());
+    }
+
+    #[tokio::test]
+    async fn cache_is_revalidated_by_import_time() {
+        let import_time = Arc::new(Mutex::new(1));
+        let transport = FakeTransport::new({
+            let import_time = import_time.clone();
+            move |_, method| match method {
+                "authenticate" => session("abc"),
+                "getLatestImportTime" => result(&import_time.lock().unwrap().to_string()),
+                _ => result("[]"),
+            }
+        });
+
+        let mut client = login_with(
+            ClientBuilder::new().cache(
+                CachePolicy::new(Duration::from_secs(60)).revalidate_interval(Duration::ZERO),
+            ),
+            transport.clone(),
+        )
+        .await;
+        client.rooms().await.unwrap();
+        client.rooms().await.unwrap();
+        *import_time.lock().unwrap() = 2;
+        client.rooms().await.unwrap();
+
+        assert_eq!(
+            transport.methods(),
+            [
+                "authenticate",
+                "getLatestImportTime",
+                "getRooms",
+                "getLatestImportTime",
+                "getLatestImportTime",
+                "getRooms"
+            ]
+        );
+    }
+}
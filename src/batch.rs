@@ -0,0 +1,172 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::marker::PhantomData;
+
+use crate::{
+    datetime::Date,
+    error::{Error, ErrorKind},
+    params,
+    resources::*,
+};
+
+/// A set of calls that are sent to Untis in a single JSON-RPC batch request.
+#[cfg_attr(
+    feature = "blocking",
+    doc = "Send it with [`Client::send_batch()`](crate::Client::send_batch) or [`AsyncClient::send_batch()`](crate::AsyncClient::send_batch)."
+)]
+#[cfg_attr(
+    not(feature = "blocking"),
+    doc = "Send it with [`AsyncClient::send_batch()`](crate::AsyncClient::send_batch)."
+)]
+///
+/// # Example
+/// ```rust,no_run
+/// # async fn run(client: &mut untis::AsyncClient) -> Result<(), untis::Error> {
+/// let mut batch = untis::Batch::new();
+/// let rooms = batch.rooms();
+/// let teachers = batch.teachers();
+///
+/// let mut results = client.send_batch(batch).await?;
+/// let rooms = results.get(rooms)?;
+/// let teachers = results.get(teachers)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Batch {
+    calls: Vec<(&'static str, serde_json::Value)>,
+    error: Option<String>,
+}
+
+/// A handle to the result of a call in a [`Batch`]. Pass it to [`BatchResults::get()`] once the batch has been sent.
+#[derive(Debug)]
+pub struct BatchCall<T> {
+    index: usize,
+    result: PhantomData<fn() -> T>,
+}
+
+impl Batch {
+    /// Creates an empty batch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of calls in this batch.
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Returns whether this batch contains no calls.
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Adds a call to the list of rooms.
+    pub fn rooms(&mut self) -> BatchCall<Vec<Room>> {
+        self.add("getRooms", ())
+    }
+
+    /// Adds a call to the list of classes.
+    pub fn classes(&mut self) -> BatchCall<Vec<Class>> {
+        self.add("getKlassen", ())
+    }
+
+    /// Adds a call to the list of subjects.
+    pub fn subjects(&mut self) -> BatchCall<Vec<Subject>> {
+        self.add("getSubjects", ())
+    }
+
+    /// Adds a call to the list of teachers.
+    pub fn teachers(&mut self) -> BatchCall<Vec<Teacher>> {
+        self.add("getTeachers", ())
+    }
+
+    /// Adds a call to the list of students.
+    pub fn students(&mut self) -> BatchCall<Vec<Student>> {
+        self.add("getStudents", ())
+    }
+
+    /// Adds a call to the list of departments.
+    pub fn departments(&mut self) -> BatchCall<Vec<Department>> {
+        self.add("getDepartments", ())
+    }
+
+    /// Adds a call to the list of exam types.
+    pub fn exam_types(&mut self) -> BatchCall<Vec<ExamType>> {
+        self.add("getExamTypes", ())
+    }
+
+    /// Adds a call to the school's timegrid.
+    pub fn timegrid(&mut self) -> BatchCall<Timegrid> {
+        self.add("getTimegridUnits", ())
+    }
+
+    /// Adds a call to the holidays in the current schoolyear.
+    pub fn holidays(&mut self) -> BatchCall<Vec<Holiday>> {
+        self.add("getHolidays", ())
+    }
+
+    /// Adds a call to the list of schoolyears.
+    pub fn schoolyears(&mut self) -> BatchCall<Vec<Schoolyear>> {
+        self.add("getSchoolyears", ())
+    }
+
+    /// Adds a call to an element's timetable between two dates.
+    pub fn timetable_between(
+        &mut self,
+        id: &usize,
+        ty: &ElementType,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> BatchCall<Vec<Lesson>> {
+        let params = params::TimetableParams::new(id, ty, start_date, end_date);
+        self.add("getTimetable", params)
+    }
+
+    pub(crate) fn add<T, P: Serialize>(&mut self, method: &'static str, params: P) -> BatchCall<T> {
+        let params = match serde_json::to_value(params) {
+            Ok(params) => params,
+            Err(err) => {
+                self.error.get_or_insert(err.to_string());
+                serde_json::Value::Null
+            }
+        };
+        self.calls.push((method, params));
+        BatchCall {
+            index: self.calls.len() - 1,
+            result: PhantomData,
+        }
+    }
+
+    /// Returns the calls, or the error that occurred while serializing any of their params.
+    pub(crate) fn into_calls(self) -> Result<Vec<(&'static str, serde_json::Value)>, Error> {
+        match self.error {
+            Some(err) => Err(ErrorKind::Serde(serde::ser::Error::custom(err)).into()),
+            None => Ok(self.calls),
+        }
+    }
+}
+
+/// The results of a sent [`Batch`].
+#[derive(Debug)]
+pub struct BatchResults {
+    results: Vec<Option<Result<serde_json::Value, Error>>>,
+}
+
+impl BatchResults {
+    pub(crate) fn new(results: Vec<Result<serde_json::Value, Error>>) -> Self {
+        Self {
+            results: results.into_iter().map(Some).collect(),
+        }
+    }
+
+    /// Takes the result of a call out of the batch. Fails if that call failed, or if the call doesn't belong to this
+    /// batch.
+    pub fn get<T: DeserializeOwned>(&mut self, call: BatchCall<T>) -> Result<T, Error> {
+        let result = self
+            .results
+            .get_mut(call.index)
+            .and_then(Option::take)
+            .unwrap_or_else(|| Err(ErrorKind::MissingResponse.into()))?;
+        Ok(serde_json::from_value(result)?)
+    }
+}
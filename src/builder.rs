@@ -0,0 +1,279 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    async_client::Endpoint,
+    error::Error,
+    jsonrpc::{self, HttpTransport, Transport},
+    AsyncClient, CachePolicy, Credentials, RateLimit, RetryPolicy, SessionToken,
+};
+
+/// Name that the client identifies itself with, unless configured otherwise.
+const DEFAULT_CLIENT_NAME: &str = "untis-rs";
+
+#[cfg_attr(
+    feature = "blocking",
+    doc = "Builder for a [`Client`](crate::Client) or [`AsyncClient`] with custom connection settings."
+)]
+#[cfg_attr(
+    not(feature = "blocking"),
+    doc = "Builder for an [`AsyncClient`] with custom connection settings."
+)]
+///
+/// # Example
+/// ```rust,no_run
+/// # #[cfg(feature = "blocking")]
+/// # fn main() -> Result<(), untis::Error> {
+/// use std::time::Duration;
+/// use untis::{ClientBuilder, Credentials};
+///
+/// let client = ClientBuilder::new()
+///     .timeout(Duration::from_secs(10))
+///     .user_agent("timetable-app/1.0")
+///     .client_name("timetable-app")
+///     .login("server.webuntis.com", "school", Credentials::password("username", "password"))?;
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "blocking"))]
+/// # fn main() {}
+/// ```
+#[derive(Clone, Default)]
+pub struct ClientBuilder {
+    timeout: Option<Duration>,
+    proxy: Option<reqwest::Proxy>,
+    user_agent: Option<String>,
+    client_name: Option<String>,
+    base_url: Option<String>,
+    http_client: Option<reqwest::Client>,
+    transport: Option<Arc<dyn Transport>>,
+    reauthenticate: bool,
+    logout_on_drop: Option<bool>,
+    lenient: bool,
+    cache: Option<CachePolicy>,
+    rate_limit: Option<RateLimit>,
+    retry: Option<RetryPolicy>,
+}
+
+impl ClientBuilder {
+    /// Creates a builder with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a timeout for each request, from connecting until the response body has been read.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends all requests through the given proxy.
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Sets the `User-Agent` header of all requests.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Sets the client name that is reported to Untis when authenticating. Defaults to `untis-rs`.
+    pub fn client_name(mut self, client_name: &str) -> Self {
+        self.client_name = Some(client_name.to_string());
+        self
+    }
+
+    /// Sets the scheme and host that requests are sent to, e.g. `http://localhost:8080` for a local stand-in.
+    /// Defaults to `https://` followed by the server.
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = Some(base_url.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Uses a pre-configured HTTP client. The [timeout](Self::timeout), [proxy](Self::proxy) and
+    /// [user agent](Self::user_agent) settings are ignored in that case.
+    pub fn http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    /// Sends all requests through a custom [`Transport`]. All HTTP related settings are ignored in that case.
+    pub fn transport<T: Transport + 'static>(mut self, transport: T) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Keeps the credentials used for logging in, to authenticate again whenever the session has expired.
+    /// See [`AsyncClient::enable_reauthentication()`].
+    ///
+    /// Has no effect when [resuming](Self::resume_async) a session, because there are no credentials to keep. Call
+    /// [`AsyncClient::enable_reauthentication()`] on the resumed client instead.
+    pub fn reauthenticate(mut self, reauthenticate: bool) -> Self {
+        self.reauthenticate = reauthenticate;
+        self
+    }
+
+    /// Sets whether the session is ended when the client is dropped. Defaults to `true` for new sessions and to
+    /// `false` for resumed ones.
+    /// See [`AsyncClient::set_logout_on_drop()`].
+    pub fn logout_on_drop(mut self, logout_on_drop: bool) -> Self {
+        self.logout_on_drop = Some(logout_on_drop);
+        self
+    }
+
+    /// Skips malformed entries in lists with a warning instead of failing the whole request.
+    /// See [`AsyncClient::set_lenient()`].
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Remembers master data and timetables. See [`CachePolicy`].
+    pub fn cache(mut self, cache: CachePolicy) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Limits how fast requests are sent. All requests of the client share the limit.
+    /// See [`RateLimit`].
+    pub fn rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Sends requests again after transient failures. See [`RetryPolicy`].
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Creates a new session and returns an [`AsyncClient`] for it.
+    pub async fn login_async(
+        self,
+        server: &str,
+        school: &str,
+        credentials: Credentials,
+    ) -> Result<AsyncClient, Error> {
+        let endpoint = self.endpoint(server, school);
+        let rpc_client = self.rpc_client(&endpoint)?;
+        let client =
+            AsyncClient::connect(rpc_client, endpoint, credentials, self.reauthenticate).await?;
+        Ok(self.configure(client))
+    }
+
+    /// Resumes a session from a [`SessionToken`] and returns an [`AsyncClient`] for it.
+    /// Fails with [`ErrorKind::InvalidSession`](crate::ErrorKind::InvalidSession) if the server no longer accepts
+    /// the session.
+    ///
+    /// The session is not logged out when the client is dropped, unless [`logout_on_drop`](Self::logout_on_drop) is
+    /// turned on explicitly. The [`reauthenticate`](Self::reauthenticate) setting is ignored, since the token contains
+    /// no credentials.
+    pub async fn resume_async(self, token: SessionToken) -> Result<AsyncClient, Error> {
+        let endpoint = self.endpoint(&token.server, &token.school);
+        let rpc_client = self.rpc_client(&endpoint)?;
+        let client = AsyncClient::reconnect(rpc_client, endpoint, token).await?;
+        Ok(self.configure(client))
+    }
+
+    /// Creates a new session and returns a [`Client`](crate::Client) for it.
+    #[cfg(feature = "blocking")]
+    pub fn login(
+        self,
+        server: &str,
+        school: &str,
+        credentials: Credentials,
+    ) -> Result<crate::Client, Error> {
+        crate::Client::wrap(self.login_async(server, school, credentials))
+    }
+
+    /// Resumes a session from a [`SessionToken`] and returns a [`Client`](crate::Client) for it.
+    /// Fails with [`ErrorKind::InvalidSession`](crate::ErrorKind::InvalidSession) if the server no longer accepts
+    /// the session.
+    ///
+    /// The session is not logged out when the client is dropped, unless [`logout_on_drop`](Self::logout_on_drop) is
+    /// turned on explicitly. The [`reauthenticate`](Self::reauthenticate) setting is ignored, since the token contains
+    /// no credentials.
+    #[cfg(feature = "blocking")]
+    pub fn resume(self, token: SessionToken) -> Result<crate::Client, Error> {
+        crate::Client::wrap(self.resume_async(token))
+    }
+
+    fn configure(&self, mut client: AsyncClient) -> AsyncClient {
+        if let Some(logout_on_drop) = self.logout_on_drop {
+            client.set_logout_on_drop(logout_on_drop);
+        }
+        client.set_lenient(self.lenient);
+        if let Some(cache) = self.cache {
+            client.enable_cache(cache);
+        }
+        client
+    }
+
+    fn endpoint(&self, server: &str, school: &str) -> Endpoint {
+        Endpoint {
+            server: server.to_string(),
+            school: school.to_string(),
+            base_url: self
+                .base_url
+                .clone()
+                .unwrap_or_else(|| format!("https://{}", server)),
+            client_name: self
+                .client_name
+                .clone()
+                .unwrap_or_else(|| DEFAULT_CLIENT_NAME.to_string()),
+        }
+    }
+
+    fn rpc_client(&self, endpoint: &Endpoint) -> Result<jsonrpc::Client, Error> {
+        let rpc_client = jsonrpc::Client::new(self.build_transport()?, &endpoint.jsonrpc_url());
+        Ok(rpc_client
+            .with_rate_limit(self.rate_limit)
+            .with_retry(self.retry))
+    }
+
+    fn build_transport(&self) -> Result<Arc<dyn Transport>, Error> {
+        if let Some(transport) = &self.transport {
+            return Ok(transport.clone());
+        }
+        if let Some(http_client) = &self.http_client {
+            return Ok(Arc::new(HttpTransport::new(http_client.clone())));
+        }
+
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        Ok(Arc::new(HttpTransport::new(builder.build()?)))
+    }
+}
+
+impl Debug for ClientBuilder {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("ClientBuilder")
+            .field("timeout", &self.timeout)
+            .field("proxy", &self.proxy)
+            .field("user_agent", &self.user_agent)
+            .field("client_name", &self.client_name)
+            .field("base_url", &self.base_url)
+            .field("http_client", &self.http_client)
+            .field("transport", &self.transport.as_ref().map(|_| "<custom>"))
+            .field("reauthenticate", &self.reauthenticate)
+            .field("logout_on_drop", &self.logout_on_drop)
+            .field("lenient", &self.lenient)
+            .field("cache", &self.cache)
+            .field("rate_limit", &self.rate_limit)
+            .field("retry", &self.retry)
+            .finish()
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Methods whose responses are cached. All of them return data that only changes when the school imports new data.
+pub(crate) const CACHED_METHODS: &[&str] = &[
+    "getStatusData",
+    "getSchoolyears",
+    "getHolidays",
+    "getRooms",
+    "getKlassen",
+    "getSubjects",
+    "getTeachers",
+    "getStudents",
+    "getDepartments",
+    "getTimegridUnits",
+    "getExamTypes",
+    "getClassregCategories",
+    "getClassregCategoryGroups",
+    "getTimetable",
+];
+
+/// Configures how long a client remembers master data and timetables.
+///
+/// Cached responses are dropped once their TTL expires, or when the school has imported new data since they were
+/// fetched. Untis is asked for its latest import time at most once per
+/// [revalidation interval](Self::revalidate_interval), which defaults to one minute.
+///
+/// # Example
+/// ```rust,no_run
+/// # #[cfg(feature = "blocking")]
+/// # fn main() -> Result<(), untis::Error> {
+/// use std::time::Duration;
+/// use untis::{CachePolicy, ClientBuilder, Credentials};
+///
+/// let mut client = ClientBuilder::new()
+///     .cache(CachePolicy::new(Duration::from_secs(60 * 60)))
+///     .login("server.webuntis.com", "school", Credentials::password("username", "password"))?;
+///
+/// let rooms = client.rooms()?;
+/// // Served from the cache.
+/// let rooms = client.rooms()?;
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "blocking"))]
+/// # fn main() {}
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct CachePolicy {
+    ttl: Duration,
+    revalidate_interval: Duration,
+}
+
+impl CachePolicy {
+    /// Keeps responses for at most `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            revalidate_interval: Duration::from_secs(60),
+        }
+    }
+
+    /// Sets how often the cache is checked against the school's latest import time. With a zero interval, every
+    /// cached response is checked before it is returned.
+    pub fn revalidate_interval(mut self, interval: Duration) -> Self {
+        self.revalidate_interval = interval;
+        self
+    }
+}
+
+/// Responses by method and params, for the import time that they were fetched at.
+#[derive(Debug)]
+pub(crate) struct Cache {
+    policy: CachePolicy,
+    entries: HashMap<String, (Instant, serde_json::Value)>,
+    import_time: Option<i64>,
+    revalidated: Option<Instant>,
+}
+
+impl Cache {
+    pub fn new(policy: CachePolicy) -> Self {
+        Self {
+            policy,
+            entries: HashMap::new(),
+            import_time: None,
+            revalidated: None,
+        }
+    }
+
+    pub fn key(method: &str, params: &serde_json::Value) -> String {
+        format!("{} {}", method, params)
+    }
+
+    /// Returns a response that hasn't expired yet.
+    pub fn get(&self, key: &str) -> Option<&serde_json::Value> {
+        self.entries
+            .get(key)
+            .filter(|(fetched, _)| fetched.elapsed() < self.policy.ttl)
+            .map(|(_, value)| value)
+    }
+
+    /// Stores a response and drops those that have expired, so that the cache doesn't grow without bounds.
+    pub fn insert(&mut self, key: String, value: serde_json::Value) {
+        let ttl = self.policy.ttl;
+        self.entries
+            .retain(|_, (fetched, _)| fetched.elapsed() < ttl);
+        self.entries.insert(key, (Instant::now(), value));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Returns whether the import time should be checked before the cache is used.
+    pub fn needs_revalidation(&self) -> bool {
+        match self.revalidated {
+            Some(revalidated) => revalidated.elapsed() >= self.policy.revalidate_interval,
+            None => true,
+        }
+    }
+
+    /// Drops all responses if the school has imported new data since they were fetched.
+    pub fn revalidate(&mut self, import_time: i64) {
+        if self.import_time != Some(import_time) {
+            self.entries.clear();
+            self.import_time = Some(import_time);
+        }
+        self.revalidated = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_drops_expired_entries() {
+        let mut cache = Cache::new(CachePolicy::new(Duration::ZERO));
+        for day in 0..3 {
+            let key = Cache::key("getTimetable", &serde_json::json!({"startDate": day}));
+            cache.insert(key, serde_json::json!([]));
+        }
+
+        assert_eq!(cache.entries.len(), 1);
+        assert_eq!(
+            cache.get(&Cache::key(
+                "getTimetable",
+                &serde_json::json!({"startDate": 2})
+            )),
+            None
+        );
+    }
+}
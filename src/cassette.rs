@@ -0,0 +1,392 @@
+//! Recording and replaying of JSON-RPC traffic, e.g. for testing applications without a live school.
+//!
+//! A [`Recorder`] wraps another [`Transport`] and writes every request/response pair to a cassette file. A
+//! [`Replayer`] serves the responses from such a file, matching requests by method and params.
+//!
+//! # Example
+//! ```rust,no_run
+//! # #[cfg(feature = "blocking")]
+//! # fn main() -> Result<(), untis::Error> {
+//! use untis::{cassette::Replayer, ClientBuilder, Credentials};
+//!
+//! let client = ClientBuilder::new()
+//!     .transport(Replayer::from_file("tests/cassettes/timetable.json")?)
+//!     .login("server.webuntis.com", "school", Credentials::password("username", "password"))?;
+//! # Ok(())
+//! # }
+//! # #[cfg(not(feature = "blocking"))]
+//! # fn main() {}
+//! ```
+//!
+//! Passwords, one-time passwords and client timestamps are removed from the recorded params, so that requests that
+//! contain them still match when replayed. Session ids, session cookies and app secrets are replaced with
+//! `<redacted>` in the recorded responses, so cassettes contain no credentials.
+//!
+//! A [`Batch`](crate::Batch) is recorded as a single interaction, whose method is the comma separated list of the
+//! batched methods and whose params are the list of their params.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{
+    error::{Error, ErrorKind},
+    jsonrpc::{self, Transport, TransportFuture, TransportRequest, TransportResponse},
+};
+
+/// Params that are removed before recording and matching.
+const VOLATILE_PARAMS: &[&str] = &["password", "otp", "clientTime"];
+
+/// Recorded in place of the session cookies that the server set.
+const REDACTED_COOKIE: &str = "JSESSIONID=<redacted>";
+
+/// A recorded request/response pair.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Interaction {
+    /// The JSON-RPC method that was called.
+    pub method: String,
+
+    /// The params that the method was called with.
+    pub params: serde_json::Value,
+
+    /// The response's HTTP status code.
+    pub status: u16,
+
+    /// `JSESSIONID=<redacted>` if the server set a session cookie. The actual cookie is never recorded.
+    pub session_cookie: Option<String>,
+
+    /// The response body, with session ids and secrets redacted.
+    pub body: String,
+}
+
+/// A list of recorded interactions.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Reads a cassette from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let text = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Writes this cassette to a JSON file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Transport that forwards requests to another transport and records them to a cassette file.
+/// The file is rewritten after every request.
+pub struct Recorder<T: Transport> {
+    inner: T,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl<T: Transport> Recorder<T> {
+    /// Records the requests sent through `inner` to the file at `path`, replacing its previous contents.
+    pub fn new(inner: T, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+            cassette: Mutex::default(),
+        }
+    }
+
+    /// Returns the interactions recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+}
+
+impl<T: Transport> Transport for Recorder<T> {
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let (method, params) = parse_request(&request)?;
+            let request_body = request.body.clone();
+            let response = self.inner.send(request).await?;
+
+            let methods: Vec<_> = method.split(',').collect();
+            let body = in_request_order(&response.body, &request_body);
+            let body = redact(&body, jsonrpc::has_secret_result(&methods));
+
+            let mut cassette = self.cassette.lock().unwrap();
+            cassette.interactions.push(Interaction {
+                method,
+                params,
+                status: response.status.as_u16(),
+                session_cookie: response
+                    .session_cookie
+                    .as_ref()
+                    .map(|_| REDACTED_COOKIE.into()),
+                body,
+            });
+            cassette.save(&self.path)?;
+
+            Ok(response)
+        })
+    }
+}
+
+/// Transport that answers requests from a [`Cassette`] instead of sending them.
+///
+/// Each request is answered with the first unused interaction that has the same method and params. Requests without
+/// such an interaction fail with [`ErrorKind::UnmatchedRequest`].
+pub struct Replayer {
+    interactions: Mutex<Vec<Option<Interaction>>>,
+}
+
+impl Replayer {
+    pub fn new(cassette: Cassette) -> Self {
+        Self {
+            interactions: Mutex::new(cassette.interactions.into_iter().map(Some).collect()),
+        }
+    }
+
+    /// Replays the cassette stored at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(Self::new(Cassette::load(path)?))
+    }
+
+    fn take(&self, method: &str, params: &serde_json::Value) -> Option<Interaction> {
+        let mut interactions = self.interactions.lock().unwrap();
+        interactions
+            .iter_mut()
+            .find(|slot| matches!(slot, Some(i) if i.method == method && i.params == *params))
+            .and_then(Option::take)
+    }
+}
+
+impl Transport for Replayer {
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let (method, params) = parse_request(&request)?;
+            let interaction = self
+                .take(&method, &params)
+                .ok_or_else(|| ErrorKind::UnmatchedRequest(format!("{} {}", method, params)))?;
+
+            Ok(TransportResponse {
+                status: reqwest::StatusCode::from_u16(interaction.status)
+                    .unwrap_or(reqwest::StatusCode::INTERNAL_SERVER_ERROR),
+                session_cookie: interaction.session_cookie,
+                body: with_request_id(&interaction.body, &request.body),
+            })
+        })
+    }
+}
+
+/// Extracts the method and the normalized params from a request.
+fn parse_request(request: &TransportRequest) -> Result<(String, serde_json::Value), Error> {
+    let value: serde_json::Value = serde_json::from_str(&request.body)?;
+    Ok(match value {
+        serde_json::Value::Array(calls) => {
+            let (methods, params): (Vec<_>, Vec<_>) = calls.into_iter().map(split_call).unzip();
+            (methods.join(","), serde_json::Value::Array(params))
+        }
+        call => split_call(call),
+    })
+}
+
+fn split_call(mut call: serde_json::Value) -> (String, serde_json::Value) {
+    let method = call["method"].as_str().unwrap_or_default().to_string();
+    let mut params = call["params"].take();
+    remove_volatile_params(&mut params);
+    (method, params)
+}
+
+fn remove_volatile_params(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|key, _| !VOLATILE_PARAMS.contains(&key.as_str()));
+            map.values_mut().for_each(remove_volatile_params);
+        }
+        serde_json::Value::Array(list) => list.iter_mut().for_each(remove_volatile_params),
+        _ => {}
+    }
+}
+
+/// Replaces session ids and secrets in a response body. Bodies that are not valid JSON are recorded as they are.
+fn redact(body: &str, redact_result: bool) -> String {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(mut value) => {
+            jsonrpc::redact_value(&mut value, redact_result);
+            value.to_string()
+        }
+        Err(_) => body.to_string(),
+    }
+}
+
+/// Replaces the ids of a recorded response with the ids of the request that is being answered.
+/// Batch responses are matched to the batched calls by position.
+fn with_request_id(body: &str, request_body: &str) -> String {
+    let response = serde_json::from_str::<serde_json::Value>(body);
+    let request = serde_json::from_str::<serde_json::Value>(request_body);
+    match (response, request) {
+        (Ok(serde_json::Value::Array(mut responses)), Ok(serde_json::Value::Array(requests))) => {
+            for (response, request) in responses.iter_mut().zip(&requests) {
+                if response.is_object() {
+                    response["id"] = request["id"].clone();
+                }
+            }
+            serde_json::Value::Array(responses).to_string()
+        }
+        (Ok(mut response), Ok(request)) if response.is_object() => {
+            response["id"] = request["id"].clone();
+            response.to_string()
+        }
+        _ => body.to_string(),
+    }
+}
+
+/// Batch responses may be in any order. They are recorded in the order of the calls, so that they can be matched by
+/// position when replayed.
+fn in_request_order(body: &str, request_body: &str) -> String {
+    let response = serde_json::from_str::<serde_json::Value>(body);
+    let request = serde_json::from_str::<serde_json::Value>(request_body);
+    let (Ok(serde_json::Value::Array(mut responses)), Ok(serde_json::Value::Array(requests))) =
+        (response, request)
+    else {
+        return body.to_string();
+    };
+
+    responses.sort_by_key(|response| {
+        requests
+            .iter()
+            .position(|request| request["id"] == response["id"])
+            .unwrap_or(usize::MAX)
+    });
+    serde_json::Value::Array(responses).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(id: &str, method: &str, params: &str) -> TransportRequest {
+        TransportRequest {
+            url: String::from("https://server/WebUntis/jsonrpc.do?school=school"),
+            cookie: None,
+            body: format!(
+                r#"{{"jsonrpc":"2.0","id":"{}","method":"{}","params":{}}}"#,
+                id, method, params
+            ),
+        }
+    }
+
+    fn interaction(method: &str, params: &str, result: &str) -> Interaction {
+        Interaction {
+            method: method.to_string(),
+            params: serde_json::from_str(params).unwrap(),
+            status: 200,
+            session_cookie: None,
+            body: format!(r#"{{"jsonrpc":"2.0","id":"1","result":{}}}"#, result),
+        }
+    }
+
+    #[tokio::test]
+    async fn replay_matches_method_and_params() {
+        let replayer = Replayer::new(Cassette {
+            interactions: vec![
+                interaction("getRooms", "{}", "[1]"),
+                interaction("getRooms", r#"{"a":1}"#, "[2]"),
+                interaction("getRooms", "{}", "[3]"),
+            ],
+        });
+
+        let response = replayer.send(request("7", "getRooms", r#"{"a":1}"#));
+        assert!(response.await.unwrap().body.contains("[2]"));
+
+        let response = replayer.send(request("8", "getRooms", "{}")).await.unwrap();
+        assert_eq!(response.body, r#"{"id":"8","jsonrpc":"2.0","result":[1]}"#);
+
+        let response = replayer.send(request("9", "getRooms", "{}"));
+        assert!(response.await.unwrap().body.contains("[3]"));
+    }
+
+    #[tokio::test]
+    async fn replay_fails_on_unmatched_request() {
+        let replayer = Replayer::new(Cassette {
+            interactions: vec![interaction("getRooms", "{}", "[]")],
+        });
+
+        let result = replayer.send(request("1", "getTeachers", "{}")).await;
+        assert!(matches!(
+            result.unwrap_err().kind(),
+            ErrorKind::UnmatchedRequest(_)
+        ));
+
+        replayer.send(request("2", "getRooms", "{}")).await.unwrap();
+        let result = replayer.send(request("3", "getRooms", "{}")).await;
+        assert!(matches!(
+            result.unwrap_err().kind(),
+            ErrorKind::UnmatchedRequest(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn record_strips_credentials() {
+        let path = std::env::temp_dir().join("untis-rs-record-strips-credentials.json");
+        let replayer = Replayer::new(Cassette {
+            interactions: vec![interaction("authenticate", r#"{"user":"u"}"#, "{}")],
+        });
+        let recorder = Recorder::new(replayer, &path);
+
+        let params = r#"{"user":"u","password":"secret"}"#;
+        recorder
+            .send(request("1", "authenticate", params))
+            .await
+            .unwrap();
+
+        let cassette = Cassette::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(cassette, recorder.cassette());
+        assert_eq!(
+            cassette.interactions[0].params,
+            serde_json::json!({"user": "u"})
+        );
+    }
+
+    #[tokio::test]
+    async fn record_redacts_sessions_and_secrets() {
+        let path = std::env::temp_dir().join("untis-rs-record-redacts-sessions.json");
+        let mut login = interaction(
+            "authenticate",
+            "{}",
+            r#"{"sessionId":"S3SS10N","personId":7}"#,
+        );
+        login.session_cookie = Some(String::from("JSESSIONID=S3SS10N"));
+        let replayer = Replayer::new(Cassette {
+            interactions: vec![
+                login,
+                interaction("getAppSharedSecret", "[]", r#""APPS3CRET""#),
+            ],
+        });
+        let recorder = Recorder::new(replayer, &path);
+
+        recorder
+            .send(request("1", "authenticate", "{}"))
+            .await
+            .unwrap();
+        recorder
+            .send(request("2", "getAppSharedSecret", "[]"))
+            .await
+            .unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(!text.contains("S3SS10N"));
+        assert!(!text.contains("APPS3CRET"));
+
+        let cassette: Cassette = serde_json::from_str(&text).unwrap();
+        let login = &cassette.interactions[0];
+        assert_eq!(login.session_cookie.as_deref(), Some(REDACTED_COOKIE));
+        assert!(login.body.contains(r#""personId":7"#));
+    }
+}
@@ -1,9 +1,18 @@
-use chrono::TimeZone;
+use serde::{de::DeserializeOwned, Serialize};
+use std::future::Future;
 
-use crate::{datetime::Date, error::Error, jsonrpc, params, resources::*, Session};
+use crate::{
+    batch::{Batch, BatchResults},
+    datetime::Date,
+    error::Error,
+    resources::*,
+    AsyncClient, CachePolicy, Credentials, Session, SessionToken, SetupLink, SkippedEntry,
+};
 
 /// Client for accessing the Untis API. Can be constructed by [`Client::login()`](Self::login) or [`School::client_login()`](School::client_login).
 ///
+/// This is a blocking wrapper around [`AsyncClient`] and must not be used from within an async runtime.
+///
 /// # Example
 /// ```rust
 /// let result = untis::Client::login("server.webuntis.com", "school", "username", "password");
@@ -15,87 +24,174 @@ use crate::{datetime::Date, error::Error, jsonrpc, params, resources::*, Session
 /// }
 /// ```
 pub struct Client {
-    rpc_client: jsonrpc::Client,
-    session: Session,
+    inner: AsyncClient,
+    runtime: tokio::runtime::Runtime,
 }
 
 impl Client {
     /// Method for creating a new session.
     /// The `server` and `school` parameter both depend on the school that the user is part of; You can get `server` from
     /// [`School.server`](crate::School::server) and `school` from [`School.login_name`](crate::School::login_name).
+    ///
+    /// Use a [`ClientBuilder`](crate::ClientBuilder) to configure timeouts, proxies and the like.
     pub fn login(
         server: &str,
         school: &str,
         username: &str,
         password: &str,
     ) -> Result<Self, Error> {
-        let params = params::AuthenticateParams {
-            client: "untis-rs",
-            user: username,
-            password,
-        };
-        let mut rpc_client = jsonrpc::Client::new(&make_untis_url(server, school));
-        let session: Session = rpc_client.request("authenticate", params)?;
-        Ok(Self {
-            rpc_client,
-            session,
-        })
+        Self::wrap(AsyncClient::login(server, school, username, password))
+    }
+
+    /// Method for creating a new session using the app secret of a user, e.g. for accounts that log in via SSO and
+    /// have no password. The one-time password is computed locally from the secret.
+    pub fn login_with_secret(
+        server: &str,
+        school: &str,
+        username: &str,
+        secret: &str,
+    ) -> Result<Self, Error> {
+        Self::wrap(AsyncClient::login_with_secret(
+            server, school, username, secret,
+        ))
+    }
+
+    /// Method for creating a new session from the QR code on a user's WebUntis profile page.
+    /// See [`SetupLink::parse()`] for turning the QR code's payload into a [`SetupLink`].
+    pub fn login_with_setup_link(link: &SetupLink) -> Result<Self, Error> {
+        Self::wrap(AsyncClient::login_with_setup_link(link))
+    }
+
+    /// Method for creating a new anonymous session, for schools that publish their timetables to guests.
+    /// The resulting [`Session`] has no person attached, so the `own_timetable_*` methods can't be used.
+    pub fn login_anonymous(server: &str, school: &str) -> Result<Self, Error> {
+        Self::wrap(AsyncClient::login_anonymous(server, school))
+    }
+
+    /// Resumes a session from a [`SessionToken`] without authenticating again.
+    /// Fails with [`ErrorKind::InvalidSession`](crate::ErrorKind::InvalidSession) if the server no longer accepts
+    /// the session.
+    ///
+    /// Unlike new sessions, resumed sessions are not logged out when the client is dropped, so that the token stays
+    /// valid. Use [`logout()`](Self::logout) to end them.
+    pub fn resume(token: SessionToken) -> Result<Self, Error> {
+        Self::wrap(AsyncClient::resume(token))
+    }
+
+    pub(crate) fn wrap<F>(login: F) -> Result<Self, Error>
+    where
+        F: Future<Output = Result<AsyncClient, Error>>,
+    {
+        let runtime = runtime()?;
+        let inner = runtime.block_on(login)?;
+        Ok(Self { inner, runtime })
+    }
+
+    /// Ends this client without logging out and returns a [`SessionToken`] that can be used to
+    /// [resume](Self::resume) the session later.
+    pub fn into_session_token(mut self) -> SessionToken {
+        self.inner.logout_on_drop = false;
+        self.inner.session_token()
     }
 
     /// Returns the active session.
     pub fn session(&self) -> &Session {
-        &self.session
+        self.inner.session()
+    }
+
+    /// Sets whether the session is ended when this client is dropped, which is the default. The result of that logout
+    /// is ignored. Turn this off for sessions that are persisted or shared elsewhere, and use
+    /// [`logout()`](Self::logout) to end them explicitly.
+    pub fn set_logout_on_drop(&mut self, logout_on_drop: bool) {
+        self.inner.set_logout_on_drop(logout_on_drop);
+    }
+
+    /// Sets whether malformed entries in lists, e.g. a lesson with a missing field, are skipped with a warning instead
+    /// of failing the whole request. See [`AsyncClient::set_lenient()`].
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.inner.set_lenient(lenient);
+    }
+
+    /// Returns the entries that were skipped in lenient mode since the last call, and forgets them.
+    pub fn take_skipped_entries(&mut self) -> Vec<SkippedEntry> {
+        self.inner.take_skipped_entries()
+    }
+
+    /// Keeps the given credentials and uses them to authenticate again, whenever the server reports that the session
+    /// is no longer valid. The failed request is then retried once with the new session.
+    pub fn enable_reauthentication(&mut self, credentials: Credentials) {
+        self.inner.enable_reauthentication(credentials);
+    }
+
+    /// Forgets the credentials passed to [`enable_reauthentication()`](Self::enable_reauthentication).
+    pub fn disable_reauthentication(&mut self) {
+        self.inner.disable_reauthentication();
+    }
+
+    /// Remembers master data and timetables, as configured by the [`CachePolicy`].
+    /// Replaces the previous cache, if there was one.
+    pub fn enable_cache(&mut self, policy: CachePolicy) {
+        self.inner.enable_cache(policy);
+    }
+
+    /// Drops all cached responses and stops caching.
+    pub fn disable_cache(&mut self) {
+        self.inner.disable_cache();
+    }
+
+    /// Drops all cached responses, so that they are fetched again on the next request.
+    pub fn clear_cache(&mut self) {
+        self.inner.clear_cache();
     }
 
     /// Returns the last time that any timetable at this school was updated.
     pub fn last_update_time(&mut self) -> Result<chrono::DateTime<chrono::Utc>, Error> {
-        let ts: i64 = self.rpc_client.request("getLatestImportTime", ())?;
-        Ok(chrono::Utc.timestamp_millis_opt(ts).unwrap())
+        self.runtime.block_on(self.inner.last_update_time())
     }
 
     /// Returns status data that can be used for displaying a timetable.
     pub fn status_data(&mut self) -> Result<StatusData, Error> {
-        self.rpc_client.request("getStatusData", ())
+        self.runtime.block_on(self.inner.status_data())
     }
 
     /// Retrieves the current schoolyear.
     pub fn current_schoolyear(&mut self) -> Result<Schoolyear, Error> {
-        self.rpc_client.request("getCurrentSchoolyear", ())
+        self.runtime.block_on(self.inner.current_schoolyear())
     }
 
     /// Retrieves a list of all schoolyears.
     pub fn schoolyears(&mut self) -> Result<Vec<Schoolyear>, Error> {
-        self.rpc_client.request("getSchoolyears", ())
+        self.runtime.block_on(self.inner.schoolyears())
     }
 
     /// Retrieves the holidays in the current schoolyear.
     pub fn holidays(&mut self) -> Result<Vec<Holiday>, Error> {
-        self.rpc_client.request("getHolidays", ())
+        self.runtime.block_on(self.inner.holidays())
     }
 
     /// Retrieves the list of rooms in the user's school.
     pub fn rooms(&mut self) -> Result<Vec<Room>, Error> {
-        self.rpc_client.request("getRooms", ())
+        self.runtime.block_on(self.inner.rooms())
     }
 
     /// Retrieves the list of classes in the user's school.
     pub fn classes(&mut self) -> Result<Vec<Class>, Error> {
-        self.rpc_client.request("getKlassen", ())
+        self.runtime.block_on(self.inner.classes())
     }
 
     /// Retrieves the list of subjects in the user's school.
     pub fn subjects(&mut self) -> Result<Vec<Subject>, Error> {
-        self.rpc_client.request("getSubjects", ())
+        self.runtime.block_on(self.inner.subjects())
     }
 
     /// Retrieves the list of teachers in the user's school.
     pub fn teachers(&mut self) -> Result<Vec<Teacher>, Error> {
-        self.rpc_client.request("getTeachers", ())
+        self.runtime.block_on(self.inner.teachers())
     }
 
     /// Retrieves the list of students in the user's school.
     pub fn students(&mut self) -> Result<Vec<Student>, Error> {
-        self.rpc_client.request("getStudents", ())
+        self.runtime.block_on(self.inner.students())
     }
 
     /// Retrieves the user's own timetable between now and a given date.
@@ -119,12 +215,8 @@ impl Client {
         start_date: &Date,
         end_date: &Date,
     ) -> Result<Vec<Lesson>, Error> {
-        self.timetable_between(
-            &self.session.person_id.clone(),
-            &self.session.person_type.clone(),
-            start_date,
-            end_date,
-        )
+        self.runtime
+            .block_on(self.inner.own_timetable_between(start_date, end_date))
     }
 
     /// Retrieves an element's timetable between now and a given date.
@@ -169,39 +261,135 @@ impl Client {
         start_date: &Date,
         end_date: &Date,
     ) -> Result<Vec<Lesson>, Error> {
-        let params = params::TimetableParams {
-            options: &params::TimetableParamsOpts {
-                element: &params::TimetableParamsElem { id, ty },
-                start_date,
-                end_date,
-                show_booking: &true,
-                show_info: &true,
-                show_subst_text: &true,
-                show_ls_text: &true,
-                show_ls_number: &true,
-                show_student_group: &true,
-                class_fields: &["id", "name"],
-                room_fields: &["id", "name"],
-                subject_fields: &["id", "name"],
-                teacher_fields: &["id", "name"],
-            },
-        };
-        self.rpc_client.request("getTimetable", params)
+        self.runtime
+            .block_on(self.inner.timetable_between(id, ty, start_date, end_date))
     }
 
     /// Retrieves the list of departments in the user's school.
     pub fn departments(&mut self) -> Result<Vec<Department>, Error> {
-        self.rpc_client.request("getDepartments", ())
+        self.runtime.block_on(self.inner.departments())
+    }
+
+    /// Retrieves the changes to the timetable between two dates for all classes of a department.
+    /// Use `0` as `department_id` to get the changes for the whole school.
+    pub fn substitutions(
+        &mut self,
+        start_date: &Date,
+        end_date: &Date,
+        department_id: &usize,
+    ) -> Result<Vec<Substitution>, Error> {
+        self.runtime.block_on(
+            self.inner
+                .substitutions(start_date, end_date, department_id),
+        )
+    }
+
+    /// Retrieves the list of exam types in the user's school.
+    pub fn exam_types(&mut self) -> Result<Vec<ExamType>, Error> {
+        self.runtime.block_on(self.inner.exam_types())
+    }
+
+    /// Retrieves the exams of a type between two dates.
+    pub fn exams(
+        &mut self,
+        exam_type_id: &usize,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<Exam>, Error> {
+        self.runtime
+            .block_on(self.inner.exams(exam_type_id, start_date, end_date))
+    }
+
+    /// Retrieves the class register entries between two dates.
+    pub fn classreg_events(
+        &mut self,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<ClassregEvent>, Error> {
+        self.runtime
+            .block_on(self.inner.classreg_events(start_date, end_date))
+    }
+
+    /// Retrieves the class register entries of a class or student between two dates.
+    pub fn classreg_events_for(
+        &mut self,
+        id: &usize,
+        ty: &ElementType,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<ClassregEvent>, Error> {
+        self.runtime
+            .block_on(self.inner.classreg_events_for(id, ty, start_date, end_date))
+    }
+
+    /// Retrieves the list of class register categories.
+    pub fn classreg_categories(&mut self) -> Result<Vec<ClassregCategory>, Error> {
+        self.runtime.block_on(self.inner.classreg_categories())
+    }
+
+    /// Retrieves the list of class register category groups.
+    pub fn classreg_category_groups(&mut self) -> Result<Vec<ClassregCategoryGroup>, Error> {
+        self.runtime.block_on(self.inner.classreg_category_groups())
+    }
+
+    /// Retrieves the lessons between two dates, together with the absences of students in them.
+    pub fn timetable_with_absences(
+        &mut self,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<LessonWithAbsences>, Error> {
+        self.runtime
+            .block_on(self.inner.timetable_with_absences(start_date, end_date))
     }
 
-    fn logout(&mut self) -> Result<(), Error> {
-        self.rpc_client.request("logout", ())
+    /// Retrieves the timegrid of the user's school.
+    pub fn timegrid(&mut self) -> Result<Timegrid, Error> {
+        self.runtime.block_on(self.inner.timegrid())
+    }
+
+    /// Calls a JSON-RPC method that has no wrapper in this crate, using this client's session.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # fn run(client: &mut untis::Client) -> Result<(), untis::Error> {
+    /// let periods: Vec<serde_json::Value> = client.call("getTimegridUnits", ())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call<T: DeserializeOwned, P: Serialize>(
+        &mut self,
+        method: &str,
+        params: P,
+    ) -> Result<T, Error> {
+        self.runtime.block_on(self.inner.call(method, params))
+    }
+
+    /// Calls a JSON-RPC method that has no wrapper in this crate and returns the raw result.
+    /// See [`call()`](Self::call).
+    pub fn call_value<P: Serialize>(
+        &mut self,
+        method: &str,
+        params: P,
+    ) -> Result<serde_json::Value, Error> {
+        self.runtime.block_on(self.inner.call_value(method, params))
+    }
+
+    /// Sends all calls of a [`Batch`] in a single request. An empty batch is not sent at all.
+    pub fn send_batch(&mut self, batch: Batch) -> Result<BatchResults, Error> {
+        self.runtime.block_on(self.inner.send_batch(batch))
+    }
+
+    /// Ends the session on the server. Unlike the logout on drop, this reports whether the logout succeeded.
+    pub fn logout(mut self) -> Result<(), Error> {
+        self.runtime.block_on(self.inner.end_session())
     }
 }
 
 impl Drop for Client {
     fn drop(&mut self) {
-        _ = self.logout();
+        if self.inner.logout_on_drop && !std::thread::panicking() {
+            _ = self.runtime.block_on(self.inner.end_session());
+        }
     }
 }
 
@@ -209,8 +397,67 @@ impl School {
     pub fn client_login(&self, username: &str, password: &str) -> Result<Client, Error> {
         Client::login(&self.server, &self.login_name, username, password)
     }
+
+    pub fn client_login_with_secret(&self, username: &str, secret: &str) -> Result<Client, Error> {
+        Client::login_with_secret(&self.server, &self.login_name, username, secret)
+    }
+
+    pub fn client_login_anonymous(&self) -> Result<Client, Error> {
+        Client::login_anonymous(&self.server, &self.login_name)
+    }
+}
+
+/// Creates the runtime that drives the blocking API.
+pub(crate) fn runtime() -> Result<tokio::runtime::Runtime, Error> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    Ok(runtime)
+}
+
+/// Runs a single future to completion on a fresh runtime.
+pub(crate) fn block_on<F: Future>(future: F) -> Result<F::Output, Error> {
+    Ok(runtime()?.block_on(future))
 }
 
-fn make_untis_url(server: &str, school: &str) -> String {
-    format!("https://{}/WebUntis/jsonrpc.do?school={}", server, school)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        async_client::tests::{result, session, FakeTransport},
+        ClientBuilder,
+    };
+
+    #[test]
+    fn drop_logs_out_once() {
+        let transport = FakeTransport::new(|_, method| match method {
+            "authenticate" => session("abc"),
+            _ => result("null"),
+        });
+        let login = || {
+            ClientBuilder::new()
+                .transport(transport.clone())
+                .login("server", "school", Credentials::password("user", "pw"))
+                .unwrap()
+        };
+
+        drop(login());
+        login().logout().unwrap();
+        let mut client = login();
+        client.set_logout_on_drop(false);
+        drop(client);
+        login().into_session_token();
+
+        assert_eq!(
+            transport.methods(),
+            [
+                "authenticate",
+                "logout",
+                "authenticate",
+                "logout",
+                "authenticate",
+                "authenticate"
+            ]
+        );
+    }
 }
@@ -1,5 +1,9 @@
-use chrono::{Datelike, Duration, Local, NaiveDate, NaiveTime};
-use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveTime, Timelike};
+use serde::{
+    self,
+    de::{self, Unexpected},
+    ser, Deserialize, Deserializer, Serialize, Serializer,
+};
 use std::ops::Deref;
 
 /// Wrapper around chrono::NaiveDate for working with Untis more easily.
@@ -59,30 +63,36 @@ impl Deref for Date {
 
 impl Serialize for Date {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        serializer.serialize_u32(chrono_to_untis_date(**self))
+        let value = chrono_to_untis_date(**self)
+            .ok_or_else(|| ser::Error::custom(format!("date {} has no 4 digit year", **self)))?;
+        serializer.serialize_u32(value)
     }
 }
 
 impl<'de> Deserialize<'de> for Date {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Date, D::Error> {
-        Ok(Date(chrono_from_untis_date(u32::deserialize(
-            deserializer,
-        )?)))
+        let value = u32::deserialize(deserializer)?;
+        let date = chrono_from_untis_date(value).ok_or_else(|| {
+            de::Error::invalid_value(Unexpected::Unsigned(value.into()), &"a date as YYYYMMDD")
+        })?;
+        Ok(Date(date))
     }
 }
 
-fn chrono_to_untis_date(date: NaiveDate) -> u32 {
-    let string = format!("{}", date.format("%Y%m%d"));
-    string.parse::<u32>().unwrap()
+/// Formats a date as `YYYYMMDD`. Returns `None` for years that don't have 4 digits.
+fn chrono_to_untis_date(date: NaiveDate) -> Option<u32> {
+    let year = u32::try_from(date.year())
+        .ok()
+        .filter(|year| (1000..=9999).contains(year))?;
+    Some(year * 10000 + date.month() * 100 + date.day())
 }
 
-fn chrono_from_untis_date(value: u32) -> NaiveDate {
-    let string = format!("{}", value);
-    let year = string[0..4].parse::<i32>().unwrap();
-    let month = string[4..6].parse::<u32>().unwrap();
-    let day = string[6..8].parse::<u32>().unwrap();
-
-    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+/// Parses a date formatted as `YYYYMMDD`.
+fn chrono_from_untis_date(value: u32) -> Option<NaiveDate> {
+    if !(10_000_000..=99_999_999).contains(&value) {
+        return None;
+    }
+    NaiveDate::from_ymd_opt((value / 10000) as i32, value / 100 % 100, value % 100)
 }
 
 /// Wrapper around chrono::NaiveDate for working with Untis more easily.
@@ -117,24 +127,22 @@ impl Serialize for Time {
 
 impl<'de> Deserialize<'de> for Time {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Time, D::Error> {
-        Ok(Time(chrono_from_untis_time(u16::deserialize(
-            deserializer,
-        )?)))
+        let value = u16::deserialize(deserializer)?;
+        let time = chrono_from_untis_time(value).ok_or_else(|| {
+            de::Error::invalid_value(Unexpected::Unsigned(value.into()), &"a time as HHMM")
+        })?;
+        Ok(Time(time))
     }
 }
 
+/// Formats a time as `HHMM`, without leading zeros. Seconds are dropped.
 fn chrono_to_untis_time(time: NaiveTime) -> u16 {
-    let string = format!("{}", time.format("%k%M"));
-    let number = string.trim().parse::<u16>().unwrap();
-    number
+    (time.hour() * 100 + time.minute()) as u16
 }
 
-fn chrono_from_untis_time(value: u16) -> NaiveTime {
-    let string = value.to_string();
-    let hour_len = if string.len() == 3 { 1 } else { 2 };
-    let hours = string[0..hour_len].parse::<u32>().unwrap();
-    let mins = string[hour_len..(hour_len + 2)].parse::<u32>().unwrap();
-    NaiveTime::from_hms_opt(hours, mins, 0).unwrap()
+/// Parses a time formatted as `HHMM`, without leading zeros.
+fn chrono_from_untis_time(value: u16) -> Option<NaiveTime> {
+    NaiveTime::from_hms_opt((value / 100).into(), (value % 100).into(), 0)
 }
 
 #[cfg(test)]
@@ -146,8 +154,8 @@ mod tests {
     #[test]
     fn convert_untis_date_forth_and_back() {
         let number = 20180316;
-        let date = chrono_from_untis_date(number);
-        let new_number = chrono_to_untis_date(date);
+        let date = chrono_from_untis_date(number).unwrap();
+        let new_number = chrono_to_untis_date(date).unwrap();
 
         assert_eq!(number, new_number);
     }
@@ -181,11 +189,61 @@ mod tests {
     #[test]
     fn convert_untis_time_forth_and_back() {
         let number = 830;
-        let time = chrono_from_untis_time(number);
+        let time = chrono_from_untis_time(number).unwrap();
         println!("Time: {}", time);
         let new_number = chrono_to_untis_time(time);
         println!("Num:  {}", new_number);
 
         assert_eq!(number, new_number);
     }
+
+    #[test]
+    fn malformed_values_are_errors() {
+        for value in ["0", "2018031", "20181301", "20180230", "-1", "\"20180316\""] {
+            assert!(serde_json::from_str::<Date>(value).is_err(), "{}", value);
+        }
+        for value in ["2400", "860", "65535", "-1", "1.5"] {
+            assert!(serde_json::from_str::<Time>(value).is_err(), "{}", value);
+        }
+    }
+
+    proptest::proptest! {
+        #[test]
+        // Every day from 1000-01-01 to 9999-12-31.
+        fn every_date_round_trips(days in 0..=3287181i64) {
+            let date = NaiveDate::from_ymd_opt(1000, 1, 1).unwrap() + Duration::days(days);
+            let json = serde_json::to_string(&Date(date)).unwrap();
+            assert_eq!(serde_json::from_str::<Date>(&json).unwrap(), Date(date));
+        }
+
+        #[test]
+        fn dates_never_panic(value: u32) {
+            let date = serde_json::from_str::<Date>(&value.to_string());
+            let expected = NaiveDate::parse_from_str(&value.to_string(), "%Y%m%d")
+                .ok()
+                .filter(|_| value >= 10_000_000);
+            assert_eq!(date.ok().map(|date| date.0), expected);
+        }
+
+        #[test]
+        fn dates_without_4_digit_years_are_not_serialized(days in -1_000_000i64..1_000_000) {
+            let date = Date(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap() + Duration::days(days));
+            let year_ok = (1000..=9999).contains(&date.year());
+            assert_eq!(serde_json::to_string(&date).is_ok(), year_ok);
+        }
+
+        #[test]
+        fn every_time_round_trips(hours in 0u32..24, minutes in 0u32..60) {
+            let time = Time(NaiveTime::from_hms_opt(hours, minutes, 0).unwrap());
+            let json = serde_json::to_string(&time).unwrap();
+            assert_eq!(json, (hours * 100 + minutes).to_string());
+            assert_eq!(serde_json::from_str::<Time>(&json).unwrap(), time);
+        }
+
+        #[test]
+        fn times_never_panic(value: u16) {
+            let time = serde_json::from_str::<Time>(&value.to_string());
+            assert_eq!(time.is_ok(), value / 100 < 24 && value % 100 < 60);
+        }
+    }
 }
@@ -4,31 +4,137 @@ use std::fmt::{self, Display, Formatter};
 use crate::jsonrpc;
 
 /// Represents all errors that can occur during an Untis API request.
+///
+/// What went wrong is described by its [`kind()`](Self::kind). Errors that occurred during a JSON-RPC call also carry
+/// the [method](Self::method) and [request id](Self::request_id) of that call.
 #[derive(Debug)]
-pub enum Error {
+pub struct Error {
+    kind: ErrorKind,
+    call: Option<Box<Call>>,
+}
+
+/// The JSON-RPC call that an [`Error`] occurred in.
+#[derive(Debug)]
+struct Call {
+    method: String,
+    request_id: String,
+}
+
+/// The different kinds of [`Error`]s.
+#[derive(Debug)]
+pub enum ErrorKind {
     /// Error during the request itself.
     Reqwest(reqwest::Error),
 
     /// Error while serializing/parsing data.
     Serde(serde_json::Error),
 
+    /// I/O error, e.g. while starting the runtime for blocking requests.
+    Io(std::io::Error),
+
     /// Error with the response HTTP status code.
     Http(reqwest::StatusCode),
 
-    /// The RPC response contained an error.
+    /// The RPC response contained an error. See [`Error::rpc_code()`] for the typed error code.
     Rpc(jsonrpc::Error),
 
+    /// The server rejected a resumed session, e.g. because it expired or was logged out,
+    /// or it didn't hand out a session at all.
+    InvalidSession,
+
+    /// The app secret used for logging in isn't valid base32.
+    InvalidSecret,
+
+    /// A WebUntis setup link couldn't be parsed.
+    InvalidSetupLink,
+
+    /// The request needs a person, but the session is anonymous.
+    Anonymous,
+
+    /// The request needs the [element type](crate::ElementType) of the session's person, but the person has none,
+    /// e.g. because it is a parent.
+    UnknownPersonType,
+
+    /// A [`Replayer`](crate::cassette::Replayer) received a request that isn't on its cassette.
+    UnmatchedRequest(String),
+
+    /// The response to a batch request contained no result for a call.
+    MissingResponse,
+
     /// No results were found.
     NotFound,
 }
 
-impl Display for Error {
+impl Error {
+    /// Returns what went wrong.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Returns what went wrong, dropping the context of the call.
+    pub fn into_kind(self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the JSON-RPC method of the call that failed, if the error occurred during a call.
+    pub fn method(&self) -> Option<&str> {
+        self.call.as_ref().map(|call| call.method.as_str())
+    }
+
+    /// Returns the id of the JSON-RPC request that failed, if the error occurred during a call.
+    pub fn request_id(&self) -> Option<&str> {
+        self.call.as_ref().map(|call| call.request_id.as_str())
+    }
+
+    /// Returns the Untis error code if this is an [`Rpc`](ErrorKind::Rpc) error.
+    pub fn rpc_code(&self) -> Option<jsonrpc::ErrorCode> {
+        match &self.kind {
+            ErrorKind::Rpc(error) => Some(error.error_code()),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the request failed because of missing, wrong or expired credentials, or a rejected session.
+    pub fn is_auth_error(&self) -> bool {
+        match &self.kind {
+            ErrorKind::InvalidSession | ErrorKind::InvalidSecret => true,
+            _ => self.rpc_code().is_some_and(|code| code.is_auth_error()),
+        }
+    }
+
+    /// Returns whether the user is not allowed to access the requested data.
+    pub fn is_permission_error(&self) -> bool {
+        self.rpc_code()
+            .is_some_and(|code| code.is_permission_error())
+    }
+
+    /// Adds the method and request id of the failed call to this error, unless it already has them.
+    pub(crate) fn with_call(mut self, method: &str, request_id: &str) -> Self {
+        if self.call.is_none() {
+            self.call = Some(Box::new(Call {
+                method: method.to_string(),
+                request_id: request_id.to_string(),
+            }));
+        }
+        self
+    }
+}
+
+impl Display for ErrorKind {
     fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
         let msg = match self {
             Self::Reqwest(err) => format!("Reqwest error: {}", err),
             Self::Serde(err) => format!("Serde Error: {}", err),
+            Self::Io(err) => format!("IO Error: {}", err),
             Self::Http(status) => format!("HTTP Error: {}", status),
             Self::Rpc(error) => format!("RPC Error: {} {}", error.code, error.message),
+            Self::InvalidSession => String::from("Session rejected by the server"),
+            Self::InvalidSecret => String::from("Invalid app secret"),
+            Self::InvalidSetupLink => String::from("Invalid setup link"),
+            Self::Anonymous => String::from("Not available for anonymous sessions"),
+            Self::UnknownPersonType => String::from("Not available for this type of user"),
+            Self::UnmatchedRequest(request) => format!("No recorded response for {}", request),
+            Self::MissingResponse => String::from("Missing response to batched call"),
             Self::NotFound => String::from("Resource not found"),
         };
 
@@ -36,14 +142,77 @@ impl Display for Error {
     }
 }
 
+impl Display for Error {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match &self.call {
+            Some(call) => write!(
+                formatter,
+                "{} (request {}) failed: {}",
+                call.method, call.request_id, self.kind
+            ),
+            None => self.kind.fmt(formatter),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Reqwest(err) => Some(err),
+            ErrorKind::Serde(err) => Some(err),
+            ErrorKind::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self { kind, call: None }
+    }
+}
+
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Self {
-        Self::Reqwest(err)
+        ErrorKind::Reqwest(err).into()
     }
 }
 
 impl From<serde_json::Error> for Error {
     fn from(err: serde_json::Error) -> Self {
-        Self::Serde(err)
+        ErrorKind::Serde(err).into()
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        ErrorKind::Io(err).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn call_context_keeps_source() {
+        let err = serde_json::from_str::<u8>("x").unwrap_err();
+        let error = Error::from(err).with_call("getRooms", "1");
+        assert!(matches!(error.kind(), ErrorKind::Serde(_)));
+        assert_eq!(error.method(), Some("getRooms"));
+        let source = error.source().unwrap();
+        assert!(source.downcast_ref::<serde_json::Error>().is_some());
+
+        let err = reqwest::Client::new().get("not a url").build().unwrap_err();
+        let error = Error::from(err).with_call("getTeachers", "2");
+        assert!(error
+            .to_string()
+            .starts_with("getTeachers (request 2) failed: Reqwest error"));
+        let source = error.source().unwrap();
+        assert!(source.downcast_ref::<reqwest::Error>().is_some());
+
+        let error = error.with_call("getRooms", "3");
+        assert_eq!(error.request_id(), Some("2"));
     }
 }
@@ -1,21 +1,120 @@
-use crate::error;
+use crate::{error, rate_limit::RateLimiter, RateLimit, RetryPolicy};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::Instrument;
 
 /// Error codes contained in [Untis API errors](Error).
 /// The underlying integer can be accessed using [code.as_isize()](Self::as_isize()).
+///
+/// Untis doesn't document its error codes. Codes that aren't known to this crate are kept as [`Unknown`](Self::Unknown).
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(from = "isize", into = "isize")]
 pub enum ErrorCode {
-    UserBlocked = -8998,
-    NotAuthenticated = -8520,
-    NoAccess = -8509,
-    InvalidCredentials = -8504,
-    InvalidSchoolName = -8500,
-    TooManyResults = -6003,
+    /// The user is blocked, e.g. after too many failed logins.
+    UserBlocked,
+    /// The session has expired or was never authenticated.
+    NotAuthenticated,
+    /// The user has no right to call the method or to access the requested element.
+    NoAccess,
+    /// The username or password is wrong.
+    InvalidCredentials,
+    /// There is no school with the given name.
+    InvalidSchoolName,
+    /// The requested date is outside of the range that the user may access.
+    NoRightForDate,
+    /// The requested schoolyear doesn't exist.
+    SchoolyearNotFound,
+    /// The element type is not valid for the request.
+    InvalidElementType,
+    /// There is no element with the requested id.
+    InvalidElementId,
+    /// The school search matched too many schools.
+    TooManyResults,
+    /// The request was not valid JSON.
+    ParseError,
+    /// The request was not a valid JSON-RPC request.
+    InvalidRequest,
+    /// The method doesn't exist.
+    MethodNotFound,
+    /// The params are not valid for the method.
+    InvalidParams,
+    /// The server failed to handle the request.
+    InternalError,
+    /// Any other error code.
+    Unknown(isize),
 }
 
 impl ErrorCode {
     pub fn as_isize(&self) -> isize {
-        *self as isize
+        match self {
+            Self::UserBlocked => -8998,
+            Self::NotAuthenticated => -8520,
+            Self::NoAccess => -8509,
+            Self::InvalidCredentials => -8504,
+            Self::InvalidSchoolName => -8500,
+            Self::NoRightForDate => -7004,
+            Self::SchoolyearNotFound => -7003,
+            Self::InvalidElementId => -7002,
+            Self::InvalidElementType => -7001,
+            Self::TooManyResults => -6003,
+            Self::ParseError => -32700,
+            Self::InvalidRequest => -32600,
+            Self::MethodNotFound => -32601,
+            Self::InvalidParams => -32602,
+            Self::InternalError => -32603,
+            Self::Unknown(code) => *code,
+        }
+    }
+
+    pub fn from_isize(code: isize) -> Self {
+        match code {
+            -8998 => Self::UserBlocked,
+            -8520 => Self::NotAuthenticated,
+            -8509 => Self::NoAccess,
+            -8504 => Self::InvalidCredentials,
+            -8500 => Self::InvalidSchoolName,
+            -7004 => Self::NoRightForDate,
+            -7003 => Self::SchoolyearNotFound,
+            -7002 => Self::InvalidElementId,
+            -7001 => Self::InvalidElementType,
+            -6003 => Self::TooManyResults,
+            -32700 => Self::ParseError,
+            -32600 => Self::InvalidRequest,
+            -32601 => Self::MethodNotFound,
+            -32602 => Self::InvalidParams,
+            -32603 => Self::InternalError,
+            code => Self::Unknown(code),
+        }
+    }
+
+    /// Returns whether the error is caused by missing, wrong or expired credentials.
+    pub fn is_auth_error(&self) -> bool {
+        matches!(
+            self,
+            Self::UserBlocked | Self::NotAuthenticated | Self::InvalidCredentials
+        )
+    }
+
+    /// Returns whether the user is not allowed to access the requested data.
+    pub fn is_permission_error(&self) -> bool {
+        matches!(self, Self::NoAccess | Self::NoRightForDate)
+    }
+}
+
+impl From<isize> for ErrorCode {
+    fn from(code: isize) -> Self {
+        Self::from_isize(code)
+    }
+}
+
+impl From<ErrorCode> for isize {
+    fn from(code: ErrorCode) -> Self {
+        code.as_isize()
     }
 }
 
@@ -28,7 +127,7 @@ pub(crate) struct Request<'a, P: Serialize> {
 }
 
 impl<'a, P: Serialize> Request<'a, P> {
-    pub fn new(id: &'a str, method: &'static str, params: P) -> Self {
+    pub fn new(id: &'a str, method: &'a str, params: P) -> Self {
         Self {
             id,
             method,
@@ -53,6 +152,14 @@ pub(crate) enum Response<T> {
     },
 }
 
+/// Fields that are never logged.
+const SECRET_FIELDS: &[&str] = &["password", "otp", "sessionId"];
+
+/// Methods whose results are never logged.
+const SECRET_RESULT_METHODS: &[&str] = &["getAppSharedSecret"];
+
+pub(crate) const REDACTED: &str = "<redacted>";
+
 /// JSON-RPC error object.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
 pub struct Error {
@@ -60,22 +167,151 @@ pub struct Error {
     pub message: String,
 }
 
+impl Error {
+    /// Returns the typed error code.
+    pub fn error_code(&self) -> ErrorCode {
+        ErrorCode::from_isize(self.code)
+    }
+}
+
+/// A serialized JSON-RPC request that is ready to be sent by a [`Transport`].
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TransportRequest {
+    /// The URL of the JSON-RPC endpoint.
+    pub url: String,
+
+    /// Value of the `Cookie` header that identifies the session, if there is one.
+    pub cookie: Option<String>,
+
+    /// The JSON-RPC request object.
+    pub body: String,
+}
+
+/// The raw response to a [`TransportRequest`].
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TransportResponse {
+    /// The response's HTTP status code.
+    pub status: reqwest::StatusCode,
+
+    /// The session cookie (`JSESSIONID=...`) if the server set one.
+    pub session_cookie: Option<String>,
+
+    /// The JSON-RPC response object.
+    pub body: String,
+}
+
+/// The future returned by [`Transport::send()`].
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<TransportResponse, error::Error>> + Send + 'a>>;
+
+/// Sends serialized JSON-RPC requests and returns the raw responses.
+///
+/// The [`HttpTransport`] is used by default. Other implementations can be passed to
+/// [`ClientBuilder::transport()`](crate::ClientBuilder::transport) and
+/// [`SchoolSearch::with_transport()`](crate::schools::SchoolSearch::with_transport), e.g. to use a different HTTP
+/// stack, to add middleware or to serve responses from memory in tests.
+pub trait Transport: Send + Sync {
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_>;
+}
+
+/// Transport that sends requests over HTTP using [reqwest](https://docs.rs/reqwest).
+#[derive(Clone, Debug, Default)]
+pub struct HttpTransport {
+    http_client: reqwest::Client,
+}
+
+impl HttpTransport {
+    pub fn new(http_client: reqwest::Client) -> Self {
+        Self { http_client }
+    }
+}
+
+impl Transport for HttpTransport {
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let mut builder = self
+                .http_client
+                .post(&request.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(request.body);
+            if let Some(cookie) = &request.cookie {
+                builder = builder.header(reqwest::header::COOKIE, cookie);
+            }
+            let response = builder.send().await?;
+
+            let status = response.status();
+            let session_cookie = response
+                .headers()
+                .get_all(reqwest::header::SET_COOKIE)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .filter_map(|value| value.split(';').next())
+                .find(|cookie| cookie.starts_with("JSESSIONID="))
+                .map(String::from);
+            let body = response.text().await?;
+
+            Ok(TransportResponse {
+                status,
+                session_cookie,
+                body,
+            })
+        })
+    }
+}
+
+#[derive(Clone)]
 pub(crate) struct Client {
-    http_client: reqwest::blocking::Client,
+    transport: Arc<dyn Transport>,
     url: String,
+    cookie: Option<String>,
     last_req_id: usize,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    retry_policy: Option<RetryPolicy>,
 }
 
 impl Client {
-    pub fn new(url: &str) -> Self {
-        let client = reqwest::blocking::Client::builder()
-            .cookie_store(true)
-            .build()
-            .unwrap();
+    pub fn new(transport: Arc<dyn Transport>, url: &str) -> Self {
+        Self {
+            transport,
+            url: url.to_string(),
+            cookie: None,
+            last_req_id: 0,
+            rate_limiter: None,
+            retry_policy: None,
+        }
+    }
+
+    /// Limits the rate of requests sent by this client and by all clients created from it with
+    /// [`with_url()`](Self::with_url).
+    pub fn with_rate_limit(mut self, limit: Option<RateLimit>) -> Self {
+        self.rate_limiter = limit.map(|limit| Arc::new(RateLimiter::new(limit)));
+        self
+    }
+
+    /// Sends failed requests again as long as the policy allows it.
+    pub fn with_retry(mut self, policy: Option<RetryPolicy>) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Returns the value of the `Cookie` header that is sent with every request.
+    pub fn cookie(&self) -> Option<&str> {
+        self.cookie.as_deref()
+    }
+
+    pub fn set_cookie(&mut self, cookie: Option<String>) {
+        self.cookie = cookie;
+    }
+
+    /// Creates a client for another endpoint that shares this client's configuration, but not its session.
+    pub fn with_url(&self, url: &str) -> Self {
         Self {
-            http_client: client,
+            transport: self.transport.clone(),
             url: url.to_string(),
+            cookie: None,
             last_req_id: 0,
+            rate_limiter: self.rate_limiter.clone(),
+            retry_policy: self.retry_policy,
         }
     }
 
@@ -84,22 +320,92 @@ impl Client {
         self.last_req_id.to_string()
     }
 
-    pub fn request<T: DeserializeOwned, P: Serialize>(
+    pub async fn request<T: DeserializeOwned, P: Serialize>(
         &mut self,
-        method: &'static str,
+        method: &str,
         params: P,
     ) -> Result<T, error::Error> {
         let req_id = &self.get_id();
-        let request = Request::new(req_id, method, params);
-        let response = self.http_client.post(&self.url).json(&request).send()?;
+        let body = serde_json::to_string(&Request::new(req_id, method, params))?;
 
-        let status = response.status();
-        if !status.is_success() {
-            return Err(error::Error::Http(status));
+        let span = tracing::debug_span!("jsonrpc_request", method, request_id = %req_id);
+        self.call(&[method], body, |client, body| client.parse_response(body))
+            .instrument(span)
+            .await
+            .map_err(|err| err.with_call(method, req_id))
+    }
+
+    /// Sends several calls as one JSON-RPC batch and returns their results in the order of `calls`.
+    /// A failing call only fails its own result, unless the server rejects the batch as a whole.
+    /// Nothing is sent for an empty batch, since JSON-RPC doesn't allow empty batch requests.
+    pub async fn request_batch(
+        &mut self,
+        calls: &[(&'static str, serde_json::Value)],
+    ) -> Result<Vec<Result<serde_json::Value, error::Error>>, error::Error> {
+        if calls.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids: Vec<String> = calls.iter().map(|_| self.get_id()).collect();
+        let requests: Vec<_> = calls
+            .iter()
+            .zip(&ids)
+            .map(|((method, params), id)| Request::new(id, method, params))
+            .collect();
+        let body = serde_json::to_string(&requests)?;
+        let methods: Vec<_> = calls.iter().map(|(method, _)| *method).collect();
+
+        let span = tracing::debug_span!(
+            "jsonrpc_request",
+            method = methods.join(","),
+            request_id = ids.join(","),
+        );
+        let results = self
+            .call(&methods, body, |client, body| {
+                client.parse_batch_response(body, &ids)
+            })
+            .instrument(span)
+            .await
+            .map_err(|err| err.with_call(&methods.join(","), &ids.join(",")))?;
+
+        Ok(results
+            .into_iter()
+            .zip(methods.iter().zip(&ids))
+            .map(|(result, (method, id))| result.map_err(|err| err.with_call(method, id)))
+            .collect())
+    }
+
+    /// Sends a request and parses the response, retrying as long as the retry policy allows it.
+    async fn call<R>(
+        &mut self,
+        methods: &[&str],
+        body: String,
+        parse: impl Fn(&Self, &str) -> Result<R, error::Error>,
+    ) -> Result<R, error::Error> {
+        let redact_result = has_secret_result(methods);
+
+        let mut attempt = 1;
+        loop {
+            let result = match self.send(body.clone(), redact_result).await {
+                Ok(response) => parse(self, &response),
+                Err(err) => Err(err),
+            };
+            let Err(err) = result else {
+                return result;
+            };
+
+            match self.retry_delay(methods, &err, attempt) {
+                Some(delay) => {
+                    tracing::warn!(attempt, ?delay, error = %err, "request failed, retrying");
+                    tokio::time::sleep(delay).await;
+                }
+                None => return Err(err),
+            }
+            attempt += 1;
         }
+    }
 
-        let text = response.text()?;
-        let response: Response<T> = serde_json::from_str(&text)?;
+    fn parse_response<T: DeserializeOwned>(&self, body: &str) -> Result<T, error::Error> {
+        let response: Response<T> = serde_json::from_str(body)?;
 
         match response {
             Response::Ok {
@@ -112,7 +418,177 @@ impl Client {
                 jsonrpc: _,
                 id: _,
                 error,
-            } => Err(error::Error::Rpc(error)),
+            } => Err(self.rpc_error(error)),
+        }
+    }
+
+    fn parse_batch_response(
+        &self,
+        body: &str,
+        ids: &[String],
+    ) -> Result<Vec<Result<serde_json::Value, error::Error>>, error::Error> {
+        let responses: Vec<Response<serde_json::Value>> = match serde_json::from_str(body) {
+            Ok(responses) => responses,
+            Err(err) => {
+                return match serde_json::from_str::<Response<serde_json::Value>>(body) {
+                    Ok(Response::Err { error, .. }) => Err(self.rpc_error(error)),
+                    _ => Err(err.into()),
+                }
+            }
+        };
+
+        let mut results: Vec<_> = ids.iter().map(|_| None).collect();
+        for response in responses {
+            let (id, result) = match response {
+                Response::Ok { id, result, .. } => (id, Ok(result)),
+                Response::Err { id, error, .. } => (id, Err(self.rpc_error(error))),
+            };
+            if let Some(index) = ids.iter().position(|req_id| id_matches(&id, req_id)) {
+                results[index] = Some(result);
+            }
+        }
+
+        Ok(results
+            .into_iter()
+            .map(|result| result.unwrap_or_else(|| Err(error::ErrorKind::MissingResponse.into())))
+            .collect())
+    }
+
+    fn retry_delay(
+        &self,
+        methods: &[&str],
+        error: &error::Error,
+        attempt: u32,
+    ) -> Option<Duration> {
+        self.retry_policy?.delay(methods, error, attempt)
+    }
+
+    /// Wraps an error response, and slows down further requests if it was caused by a failed login.
+    fn rpc_error(&self, error: Error) -> error::Error {
+        tracing::debug!(code = error.code, message = %error.message, "untis returned an error");
+        let auth_failed = matches!(
+            error.error_code(),
+            ErrorCode::InvalidCredentials | ErrorCode::UserBlocked
+        );
+        if auth_failed {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.auth_failed();
+            }
+        }
+        error::ErrorKind::Rpc(error).into()
+    }
+
+    async fn send(&mut self, body: String, redact_result: bool) -> Result<String, error::Error> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+
+        tracing::trace!(body = %redact(&body, false), "sending request");
+        let request = TransportRequest {
+            url: self.url.clone(),
+            cookie: self.cookie.clone(),
+            body,
+        };
+        let started = Instant::now();
+        let response = self.transport.send(request).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let response = match response {
+            Ok(response) => response,
+            Err(err) => {
+                tracing::debug!(latency_ms, error = %err, "request failed");
+                return Err(err);
+            }
+        };
+
+        let status = response.status;
+        tracing::debug!(latency_ms, status = status.as_u16(), "received response");
+        tracing::trace!(body = %redact(&response.body, redact_result), "response body");
+        if !status.is_success() {
+            return Err(error::ErrorKind::Http(status).into());
+        }
+
+        if let Some(cookie) = response.session_cookie {
+            self.cookie = Some(cookie);
+        }
+
+        Ok(response.body)
+    }
+}
+
+/// Replaces credentials, one-time passwords and session ids in a request or response body, so that it can be logged.
+/// The whole result is replaced if `redact_result` is set, e.g. for responses that contain a secret.
+fn redact(body: &str, redact_result: bool) -> String {
+    let Ok(mut value) = serde_json::from_str::<serde_json::Value>(body) else {
+        return format!("<{} bytes that are not valid JSON>", body.len());
+    };
+    redact_value(&mut value, redact_result);
+    value.to_string()
+}
+
+/// Like [`redact()`], but in place.
+pub(crate) fn redact_value(value: &mut serde_json::Value, redact_result: bool) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if SECRET_FIELDS.contains(&key.as_str()) || (redact_result && key == "result") {
+                    *value = serde_json::Value::from(REDACTED);
+                } else {
+                    redact_value(value, redact_result);
+                }
+            }
         }
+        serde_json::Value::Array(list) => list
+            .iter_mut()
+            .for_each(|value| redact_value(value, redact_result)),
+        _ => {}
+    }
+}
+
+/// Returns whether the result of any of these methods has to be redacted.
+pub(crate) fn has_secret_result(methods: &[&str]) -> bool {
+    methods.iter().any(|m| SECRET_RESULT_METHODS.contains(m))
+}
+
+/// Servers may answer with numeric ids, even though string ids were sent.
+fn id_matches(id: &serde_json::Value, req_id: &str) -> bool {
+    match id {
+        serde_json::Value::String(id) => id == req_id,
+        serde_json::Value::Number(id) => id.to_string() == req_id,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_codes_round_trip() {
+        for code in [-8998, -8509, -7004, -32601, -6003, -1234] {
+            assert_eq!(ErrorCode::from_isize(code).as_isize(), code);
+        }
+        assert_eq!(ErrorCode::from_isize(-1234), ErrorCode::Unknown(-1234));
+
+        let code: ErrorCode = serde_json::from_str("-8520").unwrap();
+        assert_eq!(code, ErrorCode::NotAuthenticated);
+        assert_eq!(serde_json::to_string(&code).unwrap(), "-8520");
+    }
+
+    #[test]
+    fn redacts_credentials() {
+        let body = r#"{"id":"1","method":"authenticate","params":{"user":"u","password":"pw"}}"#;
+        assert_eq!(
+            redact(body, false),
+            r#"{"id":"1","method":"authenticate","params":{"password":"<redacted>","user":"u"}}"#
+        );
+
+        let body = r#"[{"id":"2","result":{"sessionId":"abc","personId":7}}]"#;
+        assert_eq!(
+            redact(body, false),
+            r#"[{"id":"2","result":{"personId":7,"sessionId":"<redacted>"}}]"#
+        );
+
+        let body = r#"{"id":"3","result":"SECRET"}"#;
+        assert_eq!(redact(body, true), r#"{"id":"3","result":"<redacted>"}"#);
     }
 }
@@ -1,12 +1,21 @@
 //! Library for accessing the [Untis](https://www.untis.at) JSON-RPC API.
 //!
 //! The core of this crate is the `untis::Client` struct. You can log in using `untis::Client::login()`.
+//! An asynchronous variant is available as `untis::AsyncClient`; the blocking API can be disabled by turning off the
+//! default `blocking` feature.
 //!
 //! ## API
 //! This client uses the public Untis JSON-RPC API, which only has read-only, limited access.
 //!
+//! ## Logging
+//! Every request is wrapped in a [`tracing`](https://docs.rs/tracing) span that records its method and id. Latency,
+//! HTTP status and Untis error codes are logged at the `debug` level, request and response bodies at the `trace`
+//! level. Passwords, one-time passwords, session ids and shared secrets are always redacted, and the session cookie is
+//! never logged.
+//!
 //! ## Examples
-//! ```rust
+//! ```rust,no_run
+//! # #[cfg(feature = "blocking")]
 //! fn main() -> Result<(), untis::Error> {
 //!   let results = untis::schools::search("School Name")?;
 //!   let school = match results.first() {
@@ -25,19 +34,39 @@
 //!
 //!   Ok(())
 //! }
+//! # #[cfg(not(feature = "blocking"))]
+//! # fn main() {}
 //! ```
 //! For more examples, see the `examples/` directory.
 
+mod async_client;
+mod batch;
+mod builder;
+mod cache;
+#[cfg(feature = "blocking")]
 mod client;
 mod datetime;
 mod error;
+mod otp;
 mod params;
+mod rate_limit;
 mod resources;
+mod retry;
+mod setup;
 
+pub mod cassette;
 pub mod jsonrpc;
 pub mod schools;
 
+pub use async_client::{AsyncClient, Credentials, SkippedEntry};
+pub use batch::{Batch, BatchCall, BatchResults};
+pub use builder::ClientBuilder;
+pub use cache::CachePolicy;
+#[cfg(feature = "blocking")]
 pub use client::Client;
 pub use datetime::*;
-pub use error::Error;
+pub use error::{Error, ErrorKind};
+pub use rate_limit::RateLimit;
 pub use resources::*;
+pub use retry::RetryPolicy;
+pub use setup::SetupLink;
@@ -0,0 +1,61 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+use crate::error::{Error, ErrorKind};
+
+/// Computes the time-based one-time password ([RFC 6238](https://www.rfc-editor.org/rfc/rfc6238)) for a base32
+/// encoded app secret, the same way the WebUntis app does: HMAC-SHA1, 30 second steps and 6 digits.
+pub(crate) fn totp(secret: &str, unix_millis: i64) -> Result<u32, Error> {
+    let secret: String = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let key = BASE32_NOPAD
+        .decode(secret.as_bytes())
+        .map_err(|_| ErrorKind::InvalidSecret)?;
+
+    let counter = unix_millis.div_euclid(30_000) as u64;
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key).map_err(|_| ErrorKind::InvalidSecret)?;
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let code = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    Ok(code % 1_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Base32 encoding of the RFC 6238 test secret "12345678901234567890".
+    const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn totp_matches_rfc_test_vectors() {
+        assert_eq!(totp(SECRET, 59_000).unwrap(), 287082);
+        assert_eq!(totp(SECRET, 1_111_111_109_000).unwrap(), 81804);
+        assert_eq!(totp(SECRET, 2_000_000_000_000).unwrap(), 279037);
+    }
+
+    #[test]
+    fn totp_accepts_lowercase_and_spaced_secrets() {
+        let secret = "gezd gnbv gy3t qojq gezd gnbv gy3t qojq";
+        assert_eq!(totp(secret, 59_000).unwrap(), 287082);
+    }
+
+    #[test]
+    fn totp_rejects_invalid_secret() {
+        assert!(matches!(
+            totp("not base32!", 0).unwrap_err().kind(),
+            ErrorKind::InvalidSecret
+        ));
+    }
+}
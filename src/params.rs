@@ -11,29 +11,76 @@ pub enum FindSchoolParams<'a> {
 
 #[derive(Serialize)]
 pub struct AuthenticateParams<'a> {
-    pub client: &'static str,
+    pub client: &'a str,
     pub user: &'a str,
     pub password: &'a str,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppSharedSecretParams<'a> {
+    pub user_name: &'a str,
+    pub password: &'a str,
+}
+
+#[derive(Serialize)]
+pub struct UserDataParams<'a> {
+    pub auth: OtpAuthParams<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtpAuthParams<'a> {
+    pub client_time: i64,
+    pub user: &'a str,
+    pub otp: u32,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TimetableParams<'a> {
-    pub options: &'a TimetableParamsOpts<'a>,
+    pub options: TimetableParamsOpts<'a>,
+}
+
+impl<'a> TimetableParams<'a> {
+    pub fn new(
+        id: &'a usize,
+        ty: &'a ElementType,
+        start_date: &'a Date,
+        end_date: &'a Date,
+    ) -> Self {
+        Self {
+            options: TimetableParamsOpts {
+                element: TimetableParamsElem { id, ty },
+                start_date,
+                end_date,
+                show_booking: true,
+                show_info: true,
+                show_subst_text: true,
+                show_ls_text: true,
+                show_ls_number: true,
+                show_student_group: true,
+                class_fields: &["id", "name"],
+                room_fields: &["id", "name"],
+                subject_fields: &["id", "name"],
+                teacher_fields: &["id", "name"],
+            },
+        }
+    }
 }
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TimetableParamsOpts<'a> {
-    pub element: &'a TimetableParamsElem<'a>,
+    pub element: TimetableParamsElem<'a>,
     pub start_date: &'a Date,
     pub end_date: &'a Date,
-    pub show_booking: &'a bool,
-    pub show_info: &'a bool,
-    pub show_subst_text: &'a bool,
-    pub show_ls_text: &'a bool,
-    pub show_ls_number: &'a bool,
-    pub show_student_group: &'a bool,
+    pub show_booking: bool,
+    pub show_info: bool,
+    pub show_subst_text: bool,
+    pub show_ls_text: bool,
+    pub show_ls_number: bool,
+    pub show_student_group: bool,
     #[serde(rename = "klasseFields")]
     pub class_fields: &'a [&'a str],
     pub room_fields: &'a [&'a str],
@@ -47,3 +94,51 @@ pub struct TimetableParamsElem<'a> {
     #[serde(rename = "type")]
     pub ty: &'a ElementType,
 }
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubstitutionsParams<'a> {
+    pub start_date: &'a Date,
+    pub end_date: &'a Date,
+    pub department_id: &'a usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExamsParams<'a> {
+    pub exam_type_id: &'a usize,
+    pub start_date: &'a Date,
+    pub end_date: &'a Date,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassregEventsParams<'a> {
+    pub start_date: &'a Date,
+    pub end_date: &'a Date,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub element: Option<TimetableParamsElem<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct TimetableWithAbsencesParams<'a> {
+    pub options: DateRangeParams<'a>,
+}
+
+impl<'a> TimetableWithAbsencesParams<'a> {
+    pub fn new(start_date: &'a Date, end_date: &'a Date) -> Self {
+        Self {
+            options: DateRangeParams {
+                start_date,
+                end_date,
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DateRangeParams<'a> {
+    pub start_date: &'a Date,
+    pub end_date: &'a Date,
+}
@@ -0,0 +1,155 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Limits how fast a client sends requests, to avoid being blocked by Untis.
+///
+/// Requests are allowed at a steady rate, with short bursts of up to [`burst`](Self::burst) requests. After a failed
+/// login all requests wait for the [`auth_failure_delay`](Self::auth_failure_delay).
+///
+/// # Example
+/// ```rust,no_run
+/// # #[cfg(feature = "blocking")]
+/// # fn main() -> Result<(), untis::Error> {
+/// use std::time::Duration;
+/// use untis::{ClientBuilder, Credentials, RateLimit};
+///
+/// let client = ClientBuilder::new()
+///     .rate_limit(RateLimit::new(2.0).burst(5).auth_failure_delay(Duration::from_secs(10)))
+///     .login("server.webuntis.com", "school", Credentials::password("username", "password"))?;
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "blocking"))]
+/// # fn main() {}
+/// ```
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RateLimit {
+    requests_per_second: f64,
+    burst: u32,
+    auth_failure_delay: Duration,
+}
+
+impl RateLimit {
+    /// Allows the given number of requests per second, without bursts and with a delay of 5 seconds after failed
+    /// logins.
+    ///
+    /// # Panics
+    /// Panics if `requests_per_second` is not a positive number.
+    pub fn new(requests_per_second: f64) -> Self {
+        assert!(
+            requests_per_second.is_finite() && requests_per_second > 0.0,
+            "requests per second must be positive"
+        );
+        Self {
+            requests_per_second,
+            burst: 1,
+            auth_failure_delay: Duration::from_secs(5),
+        }
+    }
+
+    /// Sets how many requests may be sent at once after the client has been idle for a while.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    /// Sets how long to wait before sending the next request after Untis rejected credentials or blocked the user.
+    pub fn auth_failure_delay(mut self, delay: Duration) -> Self {
+        self.auth_failure_delay = delay;
+        self
+    }
+}
+
+/// Token bucket that is shared by all requests of a client.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    limit: RateLimit,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    /// Available requests, negative if requests are already waiting.
+    tokens: f64,
+    updated: Instant,
+    blocked_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            state: Mutex::new(State {
+                tokens: limit.burst as f64,
+                updated: Instant::now(),
+                blocked_until: None,
+            }),
+        }
+    }
+
+    /// Waits until the next request may be sent.
+    pub async fn acquire(&self) {
+        let now = Instant::now();
+        let ready = self.reserve(now);
+        if ready > now {
+            tracing::debug!(delay = ?(ready - now), "waiting for rate limit");
+            tokio::time::sleep_until(ready.into()).await;
+        }
+    }
+
+    /// Delays all following requests after a failed login.
+    pub fn auth_failed(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.blocked_until = Some(Instant::now() + self.limit.auth_failure_delay);
+    }
+
+    /// Takes a token and returns when the request that it belongs to may be sent.
+    fn reserve(&self, now: Instant) -> Instant {
+        let mut state = self.state.lock().unwrap();
+
+        let elapsed = now.saturating_duration_since(state.updated).as_secs_f64();
+        let burst = self.limit.burst as f64;
+        state.tokens = (state.tokens + elapsed * self.limit.requests_per_second).min(burst);
+        state.updated = now;
+        state.tokens -= 1.0;
+
+        let mut ready = now;
+        if state.tokens < 0.0 {
+            ready += Duration::from_secs_f64(-state.tokens / self.limit.requests_per_second);
+        }
+        match state.blocked_until {
+            Some(until) if until > ready => until,
+            _ => ready,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_bursts_then_steady_rate() {
+        let limiter = RateLimiter::new(RateLimit::new(2.0).burst(3));
+        let start = Instant::now();
+
+        let delays: Vec<_> = (0..5).map(|_| limiter.reserve(start) - start).collect();
+        assert_eq!(delays[..3], [Duration::ZERO; 3]);
+        assert_eq!(delays[3], Duration::from_millis(500));
+        assert_eq!(delays[4], Duration::from_millis(1000));
+
+        let later = start + Duration::from_secs(10);
+        assert_eq!(limiter.reserve(later), later);
+    }
+
+    #[test]
+    fn waits_after_auth_failure() {
+        let delay = Duration::from_secs(30);
+        let limiter = RateLimiter::new(RateLimit::new(10.0).auth_failure_delay(delay));
+
+        limiter.auth_failed();
+        let ready = limiter.reserve(Instant::now());
+        assert!(ready - Instant::now() > delay - Duration::from_secs(1));
+    }
+}
@@ -1,7 +1,13 @@
 use crate::datetime::{Date, Time};
+use chrono::{Datelike, Weekday};
 use serde::{Deserialize, Serialize};
 use serde_repr::*;
-use std::collections::HashMap;
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut, RangeInclusive},
+};
 
 /// The different types of elements that exist in the Untis API.
 #[derive(Serialize_repr, Deserialize_repr, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
@@ -26,6 +32,29 @@ pub(crate) struct SchoolSearchResult {
     pub schools: Vec<School>,
 }
 
+#[derive(Clone, Eq, PartialEq, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UserDataResult {
+    #[serde(default)]
+    pub user_data: UserData,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UserData {
+    pub elem_type: Option<String>,
+    pub elem_id: Option<usize>,
+    #[serde(default)]
+    pub klassen_ids: Vec<usize>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PeriodsWithAbsences<T> {
+    #[serde(default = "Vec::new")]
+    pub periods_with_absences: Vec<T>,
+}
+
 /// A school that uses Untis.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -54,6 +83,10 @@ pub struct School {
     pub server_url: String,
 
     pub mobile_service_url: Option<String>,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 /// A Untis session.
@@ -63,15 +96,51 @@ pub struct Session {
     /// The session's id.
     pub session_id: String,
 
-    /// Id of the user's class.
-    #[serde(rename = "klasseId")]
-    pub class_id: usize,
+    /// Id of the user's class. `None` for anonymous sessions.
+    #[serde(rename = "klasseId", default)]
+    pub class_id: Option<usize>,
+
+    /// The user's id. `None` for anonymous sessions.
+    #[serde(default)]
+    pub person_id: Option<usize>,
+
+    /// The user's element type (Teacher or Student). `None` for anonymous sessions, and for users whose type has no
+    /// [`ElementType`], e.g. parents.
+    #[serde(default)]
+    pub person_type: Option<ElementType>,
+
+    /// Whether this is a guest session, created with [`Credentials::Anonymous`](crate::Credentials::Anonymous).
+    #[serde(default)]
+    pub anonymous: bool,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
+}
 
-    /// The user's id.
-    pub person_id: usize,
+impl Session {
+    /// Returns whether this is a guest session, which has no person attached.
+    pub fn is_anonymous(&self) -> bool {
+        self.anonymous
+    }
+}
 
-    /// The user's element type (Teacher or Student).
-    pub person_type: ElementType,
+/// Everything needed to resume a session without authenticating again.
+/// Can be obtained from [`AsyncClient::into_session_token()`](crate::AsyncClient::into_session_token) or its blocking
+/// counterpart.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+pub struct SessionToken {
+    /// The Untis instance that the session belongs to, e.g. `ajax.webuntis.com`
+    pub server: String,
+
+    /// The [login name](School::login_name) of the session's school.
+    pub school: String,
+
+    /// The session itself.
+    pub session: Session,
+
+    /// The value of the `Cookie` header that identifies the session.
+    pub cookie: String,
 }
 
 /// A set of colors that can be used to display a timetable.
@@ -82,6 +151,10 @@ pub struct StatusData {
 
     /// Color information for lesson statuses.
     pub codes: Vec<HashMap<String, StatusDataItem>>,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 /// Color information to display a specific lesson.
@@ -93,6 +166,10 @@ pub struct StatusDataItem {
 
     /// Background color, formatted as `RRGGBB`.
     pub back_color: String,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 /// A schoolyear.
@@ -110,6 +187,10 @@ pub struct Schoolyear {
 
     /// The schoolyear's end date.
     pub end_date: Date,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 /// A school holiday.
@@ -130,6 +211,10 @@ pub struct Holiday {
 
     /// The holiday's end date.
     pub end_date: Date,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 /// Represents a room for school lessons.
@@ -158,6 +243,10 @@ pub struct Room {
     pub building: String,
 
     pub did: Option<usize>,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 /// Represents a school class.
@@ -191,6 +280,10 @@ pub struct Class {
     /// Id of the class's secondary teacher. May be -1 if there is none.
     #[serde(default = "default_id")]
     pub teacher2: isize,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 /// Represents a school subject.
@@ -216,6 +309,10 @@ pub struct Subject {
 
     /// Background color for displaying the subject, formatted as `RRGGBB`.
     pub back_color: Option<String>,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 /// Represents a teacher.
@@ -243,11 +340,19 @@ pub struct Teacher {
     pub active: bool,
 
     pub dids: Vec<DidItem>,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
 pub struct DidItem {
     id: usize,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 /// Represents a student.
@@ -272,6 +377,10 @@ pub struct Student {
 
     /// The student's gender.
     pub gender: String,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 /// A school lesson.
@@ -330,6 +439,10 @@ pub struct Lesson {
     /// The lesson's activity type.
     #[serde(default = "default_activity_type")]
     pub activity_type: String,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 /// Represents the status of a lesson (regular, cancelled, etc.)
@@ -386,6 +499,10 @@ pub struct IdItem {
     /// If this element is a substitute, this is the id of the original element.
     #[serde(rename = "original_id")]
     pub orgid: Option<isize>,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 /// Represents a school department.
@@ -400,6 +517,541 @@ pub struct Department {
 
     /// The department's full name.
     pub long_name: String,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// A change to the regular timetable, as shown on a substitution plan.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Substitution {
+    /// The kind of change.
+    #[serde(rename = "type")]
+    pub ty: SubstitutionType,
+
+    /// Id of the lesson that is changed.
+    #[serde(rename = "lsid")]
+    pub lesson_id: usize,
+
+    /// The date of the changed lesson.
+    pub date: Date,
+
+    /// The start time of the changed lesson.
+    pub start_time: Time,
+
+    /// The end time of the changed lesson.
+    pub end_time: Time,
+
+    /// The classes of the lesson.
+    #[serde(rename = "kl", default)]
+    pub classes: Vec<SubstitutionElement>,
+
+    /// The teachers of the lesson, with the originally planned teachers if they were replaced.
+    #[serde(rename = "te", default)]
+    pub teachers: Vec<SubstitutionElement>,
+
+    /// The subjects of the lesson.
+    #[serde(rename = "su", default)]
+    pub subjects: Vec<SubstitutionElement>,
+
+    /// The rooms of the lesson, with the originally planned rooms if they were replaced.
+    #[serde(rename = "ro", default)]
+    pub rooms: Vec<SubstitutionElement>,
+
+    /// Text that explains the change.
+    #[serde(rename = "txt")]
+    pub text: Option<String>,
+
+    /// Where a [shifted](SubstitutionType::Shift) lesson has been moved to, or moved from.
+    pub reschedule: Option<Reschedule>,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// The kind of change of a [`Substitution`].
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum SubstitutionType {
+    /// The lesson is cancelled.
+    #[serde(rename = "cancel")]
+    Cancel,
+    /// Another teacher holds the lesson.
+    #[serde(rename = "subst")]
+    Substitution,
+    /// An additional lesson.
+    #[serde(rename = "add")]
+    Addition,
+    /// The lesson is moved to another time.
+    #[serde(rename = "shift")]
+    Shift,
+    /// The lesson takes place in another room.
+    #[serde(rename = "rmchg")]
+    RoomChange,
+    /// The room is locked.
+    #[serde(rename = "rmlk")]
+    RoomLock,
+    /// A break supervision.
+    #[serde(rename = "bs")]
+    BreakSupervision,
+    /// An office hour.
+    #[serde(rename = "oh")]
+    OfficeHour,
+    /// A standby.
+    #[serde(rename = "sb")]
+    Standby,
+    /// Any other change, e.g. `free`, `exam` or `stxt`, with the name used by Untis.
+    #[serde(untagged)]
+    Other(String),
+}
+
+/// A class, teacher, subject or room of a [`Substitution`].
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+pub struct SubstitutionElement {
+    /// The element's id.
+    pub id: isize,
+
+    /// The element's short name.
+    #[serde(default)]
+    pub name: String,
+
+    /// If this element replaces another one, this is the id of the original element.
+    #[serde(rename = "orgid")]
+    pub original_id: Option<isize>,
+
+    /// If this element replaces another one, this is the short name of the original element.
+    #[serde(rename = "orgname")]
+    pub original_name: Option<String>,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// The other time of a shifted lesson.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reschedule {
+    /// The lesson's other date.
+    pub date: Date,
+
+    /// The lesson's other start time.
+    pub start_time: Time,
+
+    /// The lesson's other end time.
+    pub end_time: Time,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// A type of exam, e.g. a written test.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExamType {
+    /// The exam type's id, unique within this school.
+    pub id: usize,
+
+    /// The exam type's shortened name.
+    pub name: String,
+
+    /// The exam type's full name.
+    pub long_name: String,
+
+    /// Whether exams of this type are shown in timetables.
+    #[serde(default)]
+    pub show_in_timetable: bool,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// A scheduled exam.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Exam {
+    /// The exam's id.
+    pub id: usize,
+
+    /// The exam's date.
+    pub date: Date,
+
+    /// The exam's start time.
+    pub start_time: Time,
+
+    /// The exam's end time.
+    pub end_time: Time,
+
+    /// Id of the subject that is examined.
+    pub subject: Option<usize>,
+
+    /// Ids of the classes that take the exam.
+    #[serde(default)]
+    pub classes: Vec<usize>,
+
+    /// Ids of the teachers that supervise the exam.
+    #[serde(default)]
+    pub teachers: Vec<usize>,
+
+    /// Ids of the rooms that the exam takes place in.
+    #[serde(default)]
+    pub rooms: Vec<usize>,
+
+    /// Ids of the students that take the exam.
+    #[serde(default)]
+    pub students: Vec<usize>,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// An entry in the class register, e.g. a remark about a student.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassregEvent {
+    /// The [key](Student::key) of the student that the entry is about.
+    #[serde(rename = "studentid")]
+    pub student_key: String,
+
+    /// The student's first name.
+    #[serde(rename = "forname", default)]
+    pub first_name: String,
+
+    /// The student's last name.
+    #[serde(rename = "surname", default)]
+    pub last_name: String,
+
+    /// The date of the entry.
+    pub date: Date,
+
+    /// The short name of the subject that the entry was made in.
+    #[serde(default)]
+    pub subject: String,
+
+    /// The reason for the entry.
+    #[serde(default)]
+    pub reason: String,
+
+    /// The entry's text.
+    #[serde(default)]
+    pub text: String,
+
+    /// Id of the entry's [category](ClassregCategory).
+    pub category_id: Option<usize>,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// A category of class register entries.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassregCategory {
+    /// The category's id, unique within this school.
+    pub id: usize,
+
+    /// The category's shortened name.
+    pub name: String,
+
+    /// The category's full name.
+    #[serde(default)]
+    pub long_name: String,
+
+    /// Id of the [group](ClassregCategoryGroup) that this category belongs to.
+    #[serde(rename = "group")]
+    pub group_id: Option<usize>,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// A group of class register categories.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassregCategoryGroup {
+    /// The group's id, unique within this school.
+    pub id: usize,
+
+    /// The group's name.
+    pub name: String,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// A lesson together with the absences of students in it, as returned by `getTimetableWithAbsences`.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LessonWithAbsences {
+    /// The id of the period. Like `getTimetable`, which reports the same id as [`Lesson::id`], Untis identifies
+    /// single occurrences of a lesson by their period id. The id of the recurring lesson is kept in
+    /// [`extra`](Self::extra) as `lessonId`.
+    pub id: usize,
+
+    /// The lesson's date.
+    #[serde(rename = "startDate")]
+    pub date: Date,
+
+    /// The lesson's start time.
+    pub start_time: Time,
+
+    /// The lesson's end time.
+    pub end_time: Time,
+
+    /// Id of the subject that is taught in this lesson.
+    pub subject_id: Option<usize>,
+
+    /// Ids of the teachers of this lesson.
+    #[serde(default)]
+    pub teacher_ids: Vec<usize>,
+
+    /// The student group that this lesson is held for.
+    pub student_group: Option<String>,
+
+    /// The absences of students in this lesson.
+    #[serde(default)]
+    pub absences: Vec<Absence>,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+impl LessonWithAbsences {
+    /// Returns the lesson from a timetable that these absences belong to.
+    pub fn lesson<'a>(&self, timetable: &'a [Lesson]) -> Option<&'a Lesson> {
+        timetable.iter().find(|lesson| lesson.id == self.id)
+    }
+}
+
+/// The absence of a student.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Absence {
+    /// The absence's id.
+    pub id: usize,
+
+    /// Id of the absent student.
+    pub student_id: usize,
+
+    /// The first day of the absence.
+    pub start_date: Date,
+
+    /// The last day of the absence.
+    pub end_date: Date,
+
+    /// The time that the absence starts on its first day.
+    pub start_time: Time,
+
+    /// The time that the absence ends on its last day.
+    pub end_time: Time,
+
+    /// Whether the absence is excused.
+    #[serde(rename = "isExcused", default)]
+    pub excused: bool,
+
+    /// The reason for the absence, e.g. illness.
+    pub reason: Option<String>,
+
+    /// A note about the absence.
+    #[serde(default)]
+    pub text: String,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// The school's timegrid, i.e. the periods of each day of the week.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Timegrid {
+    /// The days that have periods.
+    pub days: Vec<TimegridDay>,
+}
+
+impl Timegrid {
+    /// Returns the periods of a day of the week, if there are any.
+    pub fn day(&self, weekday: Weekday) -> Option<&TimegridDay> {
+        self.days.iter().find(|day| day.weekday() == Some(weekday))
+    }
+
+    /// Returns the numbers of the first and last period of a lesson, e.g. `3..=4` for a double lesson in the 3rd and
+    /// 4th period. Returns `None` if the lesson doesn't start and end with a period of its day.
+    pub fn periods(&self, lesson: &Lesson) -> Option<RangeInclusive<usize>> {
+        let day = self.day(lesson.date.weekday())?;
+        let first = day.period_starting_at(lesson.start_time)?;
+        let last = day.period_ending_at(lesson.end_time)?;
+        Some(first..=last).filter(|periods| !periods.is_empty())
+    }
+
+    /// Returns the lessons that don't line up with the periods of their day.
+    pub fn misaligned_lessons<'a>(&self, lessons: &'a [Lesson]) -> Vec<&'a Lesson> {
+        lessons
+            .iter()
+            .filter(|lesson| self.periods(lesson).is_none())
+            .collect()
+    }
+}
+
+/// The periods of a day of the week.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimegridDay {
+    /// The day of the week, from 1 for Sunday to 7 for Saturday.
+    pub day: u8,
+
+    /// The day's periods in chronological order.
+    pub time_units: Vec<TimeUnit>,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+impl TimegridDay {
+    /// Returns the day of the week, or `None` if Untis sent an invalid day.
+    pub fn weekday(&self) -> Option<Weekday> {
+        if !(1..=7).contains(&self.day) {
+            return None;
+        }
+        // chrono counts from 0 for Monday.
+        Weekday::try_from((self.day + 5) % 7).ok()
+    }
+
+    /// Returns the number of the period that starts at the given time, counting from 1.
+    pub fn period_starting_at(&self, time: Time) -> Option<usize> {
+        let index = self
+            .time_units
+            .iter()
+            .position(|unit| unit.start_time == time)?;
+        Some(index + 1)
+    }
+
+    /// Returns the number of the period that ends at the given time, counting from 1.
+    pub fn period_ending_at(&self, time: Time) -> Option<usize> {
+        let index = self
+            .time_units
+            .iter()
+            .position(|unit| unit.end_time == time)?;
+        Some(index + 1)
+    }
+
+    /// Returns the gaps between consecutive periods.
+    pub fn breaks(&self) -> Vec<Break> {
+        self.time_units
+            .windows(2)
+            .filter(|units| units[0].end_time < units[1].start_time)
+            .map(|units| Break {
+                start_time: units[0].end_time,
+                end_time: units[1].start_time,
+            })
+            .collect()
+    }
+}
+
+/// A period in the timegrid.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeUnit {
+    /// The period's name, usually its number.
+    pub name: String,
+
+    /// The period's start time.
+    pub start_time: Time,
+
+    /// The period's end time.
+    pub end_time: Time,
+
+    /// Fields that this crate doesn't know about.
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// A break between two periods.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Break {
+    /// The end of the period before the break.
+    pub start_time: Time,
+
+    /// The start of the period after the break.
+    pub end_time: Time,
+}
+
+/// Fields of a resource that this crate doesn't know about, e.g. because Untis added them after this crate was
+/// released. Keys are the field names used by Untis.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Extra(pub serde_json::Map<String, serde_json::Value>);
+
+impl Deref for Extra {
+    type Target = serde_json::Map<String, serde_json::Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Extra {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+// JSON values can't be hashed or ordered, so their serialized form is used instead. Keys are sorted first, because
+// maps compare equal regardless of their order if serde_json's `preserve_order` feature is enabled.
+impl Extra {
+    fn sorted_entries(&self) -> Vec<(&String, String)> {
+        let mut entries: Vec<_> = self
+            .0
+            .iter()
+            .map(|(key, value)| (key, sorted(value).to_string()))
+            .collect();
+        entries.sort();
+        entries
+    }
+}
+
+/// Returns a copy of a JSON value whose objects are sorted by key.
+fn sorted(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            let entries = entries
+                .into_iter()
+                .map(|(key, value)| (key.clone(), sorted(value)));
+            serde_json::Value::Object(entries.collect())
+        }
+        serde_json::Value::Array(list) => list.iter().map(sorted).collect(),
+        value => value.clone(),
+    }
+}
+
+impl Hash for Extra {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.sorted_entries().hash(state);
+    }
+}
+
+impl PartialOrd for Extra {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Extra {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sorted_entries().cmp(&other.sorted_entries())
+    }
 }
 
 fn default_id() -> isize {
@@ -409,3 +1061,201 @@ fn default_id() -> isize {
 fn default_activity_type() -> String {
     String::from("undefined")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonrpc;
+
+    fn timegrid() -> Timegrid {
+        serde_json::from_str(
+            r#"[{"day":2,"timeUnits":[
+                {"name":"1","startTime":800,"endTime":845},
+                {"name":"2","startTime":845,"endTime":930},
+                {"name":"3","startTime":950,"endTime":1035}
+            ]}]"#,
+        )
+        .unwrap()
+    }
+
+    fn lesson(date: u32, start_time: u16, end_time: u16) -> Lesson {
+        serde_json::from_value(serde_json::json!({
+            "id": 1, "date": date, "startTime": start_time, "endTime": end_time,
+            "lsnumber": 1, "kl": [], "su": [], "ro": []
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn lessons_map_to_periods() {
+        let timegrid = timegrid();
+        let monday = 20240304;
+
+        assert_eq!(timegrid.periods(&lesson(monday, 845, 1035)), Some(2..=3));
+        assert_eq!(timegrid.periods(&lesson(monday, 800, 845)), Some(1..=1));
+        assert_eq!(timegrid.periods(&lesson(monday, 900, 1035)), None);
+        assert_eq!(timegrid.periods(&lesson(monday + 1, 800, 845)), None);
+
+        let lessons = [lesson(monday, 800, 930), lesson(monday, 930, 950)];
+        assert_eq!(timegrid.misaligned_lessons(&lessons), [&lessons[1]]);
+    }
+
+    #[test]
+    fn substitutions_keep_original_elements() {
+        let substitution: Substitution = serde_json::from_str(
+            r#"{"type":"shift","lsid":7,"date":20240304,"startTime":800,"endTime":845,
+                "kl":[{"id":1,"name":"1a"}],
+                "te":[{"id":3,"name":"SMI","orgid":2,"orgname":"DOE"}],
+                "su":[{"id":4,"name":"MA"}],
+                "ro":[{"id":5,"name":"R1"}],
+                "txt":"moved",
+                "reschedule":{"date":20240305,"startTime":950,"endTime":1035}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(substitution.ty, SubstitutionType::Shift);
+        assert_eq!(substitution.teachers[0].original_id, Some(2));
+        assert_eq!(
+            substitution.teachers[0].original_name.as_deref(),
+            Some("DOE")
+        );
+        assert_eq!(substitution.classes[0].original_id, None);
+        assert_eq!(substitution.reschedule.unwrap().date.day(), 5);
+    }
+
+    #[test]
+    fn substitution_types_round_trip() {
+        for (name, ty) in [
+            ("cancel", SubstitutionType::Cancel),
+            ("rmchg", SubstitutionType::RoomChange),
+            ("free", SubstitutionType::Other(String::from("free"))),
+        ] {
+            let substitution: Substitution = serde_json::from_value(serde_json::json!({
+                "type": name, "lsid": 7, "date": 20240304, "startTime": 800, "endTime": 845
+            }))
+            .unwrap();
+            assert_eq!(substitution.ty, ty);
+
+            let value = serde_json::to_value(&substitution).unwrap();
+            assert_eq!(value["type"], name);
+            assert_eq!(
+                serde_json::from_value::<Substitution>(value).unwrap(),
+                substitution
+            );
+        }
+    }
+
+    #[test]
+    fn exams_list_their_elements() {
+        let exams: Vec<Exam> = serde_json::from_str(
+            r#"[{"id":12,"date":20240304,"startTime":800,"endTime":945,"subject":4,
+                "classes":[1,2],"teachers":[3],"rooms":[5],"students":[8,9],"name":"Test"}]"#,
+        )
+        .unwrap();
+        let exam = &exams[0];
+
+        assert_eq!(exam.id, 12);
+        assert_eq!(exam.date.day(), 4);
+        assert_eq!(
+            exam.start_time.0,
+            chrono::NaiveTime::from_hms_opt(8, 0, 0).unwrap()
+        );
+        assert_eq!(
+            exam.end_time.0,
+            chrono::NaiveTime::from_hms_opt(9, 45, 0).unwrap()
+        );
+        assert_eq!(exam.subject, Some(4));
+        assert_eq!(exam.classes, [1, 2]);
+        assert_eq!(exam.teachers, [3]);
+        assert_eq!(exam.rooms, [5]);
+        assert_eq!(exam.students, [8, 9]);
+        assert_eq!(exam.extra.get("name"), Some(&serde_json::json!("Test")));
+
+        let exam_type: ExamType = serde_json::from_str(
+            r#"{"id":1,"name":"SA","longName":"Schularbeit","showInTimetable":true}"#,
+        )
+        .unwrap();
+        assert_eq!(exam_type.long_name, "Schularbeit");
+        assert!(exam_type.show_in_timetable);
+    }
+
+    #[test]
+    fn classreg_events_use_untis_names() {
+        let event: ClassregEvent = serde_json::from_str(
+            r#"{"studentid":"JD01","forname":"Jane","surname":"Doe","date":20240304,
+                "subject":"MA","reason":"Homework","text":"missing","categoryId":3}"#,
+        )
+        .unwrap();
+        assert_eq!(event.student_key, "JD01");
+        assert_eq!(event.first_name, "Jane");
+        assert_eq!(event.last_name, "Doe");
+        assert_eq!(event.date.day(), 4);
+        assert_eq!(event.category_id, Some(3));
+
+        let category: ClassregCategory =
+            serde_json::from_str(r#"{"id":3,"name":"HW","longName":"Homework","group":1}"#)
+                .unwrap();
+        assert_eq!(category.group_id, Some(1));
+        assert!(category.extra.is_empty());
+    }
+
+    #[test]
+    fn absences_belong_to_lessons() {
+        let response: jsonrpc::Response<PeriodsWithAbsences<LessonWithAbsences>> = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","id":"1","result":{"periodsWithAbsences":[{
+                "id":1,"lessonId":42,"startDate":20240304,"endDate":20240304,"startTime":800,"endTime":845,
+                "klasseIds":[3],"subjectId":4,"teacherIds":[5],"studentGroup":"1a_MA",
+                "absences":[{"id":9,"studentId":4,"startDate":20240304,"endDate":20240304,
+                    "startTime":800,"endTime":1035,"isExcused":true,"reason":"ill","text":""}]}]}}"#,
+        )
+        .unwrap();
+        let jsonrpc::Response::Ok { result, .. } = response else {
+            panic!("expected a result");
+        };
+        let period = &result.periods_with_absences[0];
+        let timetable = [lesson(20240304, 800, 845)];
+
+        assert_eq!(period.subject_id, Some(4));
+        assert_eq!(period.teacher_ids, [5]);
+        assert_eq!(period.student_group.as_deref(), Some("1a_MA"));
+        assert_eq!(period.extra.get("lessonId"), Some(&serde_json::json!(42)));
+        assert_eq!(period.absences[0].student_id, 4);
+        assert!(period.absences[0].excused);
+        assert_eq!(period.absences[0].reason.as_deref(), Some("ill"));
+        assert_eq!(period.lesson(&timetable), Some(&timetable[0]));
+    }
+
+    #[test]
+    fn extra_is_ordered_by_sorted_keys() {
+        let extra = |json: serde_json::Value| Extra(json.as_object().unwrap().clone());
+        let a = extra(serde_json::json!({"b": {"y": 1, "x": 2}, "a": 1}));
+        let mut reordered = Extra::default();
+        reordered.insert(String::from("a"), serde_json::json!(1));
+        reordered.insert(String::from("b"), serde_json::json!({"x": 2, "y": 1}));
+
+        let hash = |extra: &Extra| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            extra.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(a, reordered);
+        assert_eq!(a.cmp(&reordered), Ordering::Equal);
+        assert_eq!(hash(&a), hash(&reordered));
+        assert!(a < extra(serde_json::json!({"a": 2})));
+    }
+
+    #[test]
+    fn breaks_are_gaps_between_periods() {
+        let timegrid = timegrid();
+        let day = timegrid.day(Weekday::Mon).unwrap();
+        assert_eq!(timegrid.days[0].weekday(), Some(Weekday::Mon));
+
+        assert_eq!(
+            day.breaks(),
+            [Break {
+                start_time: Time(chrono::NaiveTime::from_hms_opt(9, 30, 0).unwrap()),
+                end_time: Time(chrono::NaiveTime::from_hms_opt(9, 50, 0).unwrap()),
+            }]
+        );
+    }
+}
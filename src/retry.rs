@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use crate::error::{Error, ErrorKind};
+
+/// Methods that create or end sessions. They are never retried after Untis answered with an error, so that rejected
+/// credentials are not sent again and again.
+const NON_IDEMPOTENT_METHODS: &[&str] = &[
+    "authenticate",
+    "logout",
+    "getAppSharedSecret",
+    "getUserData2017",
+];
+
+/// Decides whether and when failed requests are sent again.
+///
+/// By default, requests are retried after [transient errors](Self::is_transient) with exponential backoff, starting
+/// at 500 milliseconds and doubling up to 10 seconds. Each delay is randomized by up to half its length, so that
+/// several clients don't retry at the same time.
+///
+/// # Example
+/// ```rust,no_run
+/// # #[cfg(feature = "blocking")]
+/// # fn main() -> Result<(), untis::Error> {
+/// use std::time::Duration;
+/// use untis::{ClientBuilder, Credentials, RetryPolicy};
+///
+/// let client = ClientBuilder::new()
+///     .retry(RetryPolicy::new(4).backoff(Duration::from_secs(1), Duration::from_secs(30)))
+///     .login("server.webuntis.com", "school", Credentials::password("username", "password"))?;
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "blocking"))]
+/// # fn main() {}
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    retry_if: fn(&Error) -> bool,
+}
+
+impl RetryPolicy {
+    /// Sends each request at most `max_attempts` times, including the first attempt.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            retry_if: Self::is_transient,
+        }
+    }
+
+    /// Sets the delay before the first retry, and the limit that the doubling delays are capped at.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Sets which errors are retried. Defaults to [`RetryPolicy::is_transient()`].
+    ///
+    /// Errors returned by Untis for logins and logouts are never retried, regardless of this setting.
+    pub fn retry_if(mut self, retry_if: fn(&Error) -> bool) -> Self {
+        self.retry_if = retry_if;
+        self
+    }
+
+    /// Returns whether an error is likely to go away on its own: `502 Bad Gateway`, `503 Service Unavailable`,
+    /// `504 Gateway Timeout`, timeouts and failed or interrupted connections.
+    pub fn is_transient(error: &Error) -> bool {
+        match error.kind() {
+            ErrorKind::Http(status) => matches!(status.as_u16(), 502..=504),
+            ErrorKind::Reqwest(err) => err.is_connect() || err.is_timeout() || err.is_request(),
+            _ => false,
+        }
+    }
+
+    /// Returns how long to wait before sending a request again, or `None` if it should not be retried.
+    /// `attempt` is the number of the attempt that just failed, starting at 1.
+    pub(crate) fn delay(&self, methods: &[&str], error: &Error, attempt: u32) -> Option<Duration> {
+        let rejected = matches!(error.kind(), ErrorKind::Rpc(_))
+            && methods.iter().any(|m| NON_IDEMPOTENT_METHODS.contains(m));
+        if attempt >= self.max_attempts || rejected || !(self.retry_if)(error) {
+            return None;
+        }
+
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_backoff);
+        let jitter = backoff.mul_f64(fastrand::f64() / 2.0);
+        Some(backoff - jitter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonrpc;
+
+    fn rpc_error() -> Error {
+        Error::from(ErrorKind::Rpc(jsonrpc::Error {
+            code: jsonrpc::ErrorCode::InvalidCredentials.as_isize(),
+            message: String::from("bad credentials"),
+        }))
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        let policy = RetryPolicy::new(5).backoff(Duration::from_secs(1), Duration::from_secs(3));
+        let error = Error::from(ErrorKind::Http(reqwest::StatusCode::BAD_GATEWAY));
+
+        let delays: Vec<_> = (1..=5)
+            .map(|attempt| policy.delay(&["getRooms"], &error, attempt))
+            .collect();
+        let bounds = [(500, 1000), (1000, 2000), (1500, 3000), (1500, 3000)];
+        for (delay, (min, max)) in delays.iter().zip(bounds) {
+            let delay = delay.unwrap();
+            assert!(delay >= Duration::from_millis(min) && delay <= Duration::from_millis(max));
+        }
+        assert_eq!(delays[4], None);
+    }
+
+    #[test]
+    fn never_retries_rejected_logins() {
+        let policy = RetryPolicy::new(3).retry_if(|_| true);
+
+        assert!(policy.delay(&["getRooms"], &rpc_error(), 1).is_some());
+        assert!(policy.delay(&["authenticate"], &rpc_error(), 1).is_none());
+
+        let error = Error::from(ErrorKind::Http(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+        assert!(policy.delay(&["authenticate"], &error, 1).is_some());
+    }
+}
@@ -1,43 +1,128 @@
+use std::sync::Arc;
+
 use crate::{
-    error::Error, jsonrpc, params::FindSchoolParams, resources::School, SchoolSearchResult,
+    error::{Error, ErrorKind},
+    jsonrpc::{self, HttpTransport, Transport},
+    params::FindSchoolParams,
+    resources::School,
+    SchoolSearchResult,
 };
 
-fn get_client() -> jsonrpc::Client {
-    jsonrpc::Client::new("https://mobile.webuntis.com/ms/schoolquery2")
-}
+const SCHOOL_SEARCH_URL: &str = "https://mobile.webuntis.com/ms/schoolquery2";
 
 /// Returns all schools matching the query or an empty vec if there are too many results.
+#[cfg(feature = "blocking")]
 pub fn search(query: &str) -> Result<Vec<School>, Error> {
-    let result = get_client().request(
-        "searchSchool",
-        vec![FindSchoolParams::Search { search: query }],
-    );
-    catch_too_many(result)
+    SchoolSearch::new()?.search(query)
 }
 
 /// Retrieves a school by its id.
+#[cfg(feature = "blocking")]
 pub fn get_by_id(id: &usize) -> Result<School, Error> {
-    let result = get_client().request(
-        "searchSchool",
-        vec![FindSchoolParams::ById { schoolid: id }],
-    );
-
-    get_first(catch_too_many(result)?)
+    SchoolSearch::new()?.get_by_id(id)
 }
 
 /// Retrieves a school by it's [`login_name`](School#structfield.login_name).
+#[cfg(feature = "blocking")]
 pub fn get_by_name(name: &str) -> Result<School, Error> {
-    let result = get_client().request(
-        "searchSchool",
-        vec![FindSchoolParams::ByName { schoolname: name }],
-    );
+    SchoolSearch::new()?.get_by_name(name)
+}
+
+/// Returns all schools matching the query or an empty vec if there are too many results.
+pub async fn search_async(query: &str) -> Result<Vec<School>, Error> {
+    SchoolSearch::new()?.search_async(query).await
+}
+
+/// Retrieves a school by its id.
+pub async fn get_by_id_async(id: &usize) -> Result<School, Error> {
+    SchoolSearch::new()?.get_by_id_async(id).await
+}
+
+/// Retrieves a school by it's [`login_name`](School#structfield.login_name).
+pub async fn get_by_name_async(name: &str) -> Result<School, Error> {
+    SchoolSearch::new()?.get_by_name_async(name).await
+}
+
+/// Searches for schools through a specific [`Transport`]. The functions in this module use the default
+/// [`HttpTransport`].
+pub struct SchoolSearch {
+    rpc_client: jsonrpc::Client,
+}
+
+impl SchoolSearch {
+    /// Creates a school search that uses the default [`HttpTransport`].
+    pub fn new() -> Result<Self, Error> {
+        let http_client = reqwest::Client::builder().build()?;
+        Ok(Self::with_transport(HttpTransport::new(http_client)))
+    }
 
-    get_first(catch_too_many(result)?)
+    /// Creates a school search that sends its requests through the given transport.
+    pub fn with_transport<T: Transport + 'static>(transport: T) -> Self {
+        Self {
+            rpc_client: jsonrpc::Client::new(Arc::new(transport), SCHOOL_SEARCH_URL),
+        }
+    }
+
+    /// Returns all schools matching the query or an empty vec if there are too many results.
+    #[cfg(feature = "blocking")]
+    pub fn search(&mut self, query: &str) -> Result<Vec<School>, Error> {
+        crate::client::block_on(self.search_async(query))?
+    }
+
+    /// Retrieves a school by its id.
+    #[cfg(feature = "blocking")]
+    pub fn get_by_id(&mut self, id: &usize) -> Result<School, Error> {
+        crate::client::block_on(self.get_by_id_async(id))?
+    }
+
+    /// Retrieves a school by it's [`login_name`](School#structfield.login_name).
+    #[cfg(feature = "blocking")]
+    pub fn get_by_name(&mut self, name: &str) -> Result<School, Error> {
+        crate::client::block_on(self.get_by_name_async(name))?
+    }
+
+    /// Returns all schools matching the query or an empty vec if there are too many results.
+    pub async fn search_async(&mut self, query: &str) -> Result<Vec<School>, Error> {
+        let result = self
+            .rpc_client
+            .request(
+                "searchSchool",
+                vec![FindSchoolParams::Search { search: query }],
+            )
+            .await;
+        catch_too_many(result)
+    }
+
+    /// Retrieves a school by its id.
+    pub async fn get_by_id_async(&mut self, id: &usize) -> Result<School, Error> {
+        let result = self
+            .rpc_client
+            .request(
+                "searchSchool",
+                vec![FindSchoolParams::ById { schoolid: id }],
+            )
+            .await;
+
+        get_first(catch_too_many(result)?)
+    }
+
+    /// Retrieves a school by it's [`login_name`](School#structfield.login_name).
+    pub async fn get_by_name_async(&mut self, name: &str) -> Result<School, Error> {
+        let result = self
+            .rpc_client
+            .request(
+                "searchSchool",
+                vec![FindSchoolParams::ByName { schoolname: name }],
+            )
+            .await;
+
+        get_first(catch_too_many(result)?)
+    }
 }
 
 fn get_first(mut list: Vec<School>) -> Result<School, Error> {
     if list.is_empty() {
-        Err(Error::NotFound)
+        Err(ErrorKind::NotFound.into())
     } else {
         Ok(list.swap_remove(0))
     }
@@ -46,13 +131,7 @@ fn get_first(mut list: Vec<School>) -> Result<School, Error> {
 fn catch_too_many(result: Result<SchoolSearchResult, Error>) -> Result<Vec<School>, Error> {
     match result {
         Ok(v) => Ok(v.schools),
-        Err(Error::Rpc(err)) => {
-            if err.code == jsonrpc::ErrorCode::TooManyResults.as_isize() {
-                Ok(vec![])
-            } else {
-                Err(Error::Rpc(err))
-            }
-        }
+        Err(err) if err.rpc_code() == Some(jsonrpc::ErrorCode::TooManyResults) => Ok(vec![]),
         Err(err) => Err(err),
     }
 }
@@ -0,0 +1,124 @@
+use std::fmt::{self, Debug, Formatter};
+use std::str::FromStr;
+
+use crate::{
+    error::{Error, ErrorKind},
+    Credentials,
+};
+
+/// The login details contained in the QR code on a user's WebUntis profile page.
+/// The QR code's payload is a URI of the form `untis://setschool?url=...&school=...&user=...&key=...`.
+///
+/// # Example
+/// ```rust
+/// let link: untis::SetupLink = "untis://setschool?url=ajax.webuntis.com&school=demo&user=jane&key=ABCDEFGH"
+///     .parse()
+///     .unwrap();
+/// assert_eq!(link.server, "ajax.webuntis.com");
+/// assert_eq!(link.login_name, "demo");
+/// ```
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct SetupLink {
+    /// The Untis instance that the school uses, e.g. `ajax.webuntis.com`
+    pub server: String,
+
+    /// The school's [login name](crate::School::login_name).
+    pub login_name: String,
+
+    /// The user's name.
+    pub user: String,
+
+    /// The user's app secret, used to generate one-time passwords.
+    pub secret: String,
+}
+
+impl SetupLink {
+    /// Parses the payload of a WebUntis setup QR code.
+    pub fn parse(uri: &str) -> Result<Self, Error> {
+        let url = reqwest::Url::parse(uri.trim()).map_err(|_| ErrorKind::InvalidSetupLink)?;
+        if url.scheme() != "untis" || url.host_str() != Some("setschool") {
+            return Err(ErrorKind::InvalidSetupLink.into());
+        }
+
+        let (mut server, mut login_name, mut user, mut secret) = (None, None, None, None);
+        for (key, value) in url.query_pairs() {
+            let value = Some(value.into_owned()).filter(|v| !v.is_empty());
+            match key.as_ref() {
+                "url" => server = value,
+                "school" => login_name = value,
+                "user" => user = value,
+                "key" => secret = value,
+                _ => {}
+            }
+        }
+
+        match (server, login_name, user, secret) {
+            (Some(server), Some(login_name), Some(user), Some(secret)) => Ok(Self {
+                server: strip_scheme(&server).to_string(),
+                login_name,
+                user,
+                secret,
+            }),
+            _ => Err(ErrorKind::InvalidSetupLink.into()),
+        }
+    }
+
+    /// Returns the credentials for logging in with this link.
+    pub fn credentials(&self) -> Credentials {
+        Credentials::secret(&self.user, &self.secret)
+    }
+}
+
+impl FromStr for SetupLink {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Debug for SetupLink {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("SetupLink")
+            .field("server", &self.server)
+            .field("login_name", &self.login_name)
+            .field("user", &self.user)
+            .field("secret", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Some versions of WebUntis put a full URL into the `url` parameter instead of just the host.
+fn strip_scheme(server: &str) -> &str {
+    let server = server
+        .strip_prefix("https://")
+        .or_else(|| server.strip_prefix("http://"))
+        .unwrap_or(server);
+    server.trim_end_matches('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_setup_link() {
+        let link = SetupLink::parse(
+            "untis://setschool?url=https://ajax.webuntis.com/&school=Demo%20School&user=jane.doe&key=ABCD2345&schoolNumber=42",
+        )
+        .unwrap();
+
+        assert_eq!(link.server, "ajax.webuntis.com");
+        assert_eq!(link.login_name, "Demo School");
+        assert_eq!(link.user, "jane.doe");
+        assert_eq!(link.secret, "ABCD2345");
+    }
+
+    #[test]
+    fn parse_setup_link_rejects_incomplete_links() {
+        assert!(SetupLink::parse("untis://setschool?url=ajax.webuntis.com&school=demo").is_err());
+        assert!(SetupLink::parse("https://setschool?url=a&school=b&user=c&key=d").is_err());
+        assert!(SetupLink::parse("not a link").is_err());
+    }
+}